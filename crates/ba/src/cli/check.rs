@@ -0,0 +1,37 @@
+use crate::compiler::Engine;
+
+use super::*;
+
+pub(super) fn subcommand() -> Command {
+    Command::new("check")
+        .alias("validate")
+        .about("Checks the specified BA application without running it")
+        .long_about("This subcommand is used to validate a specified 'Beaulieu Automation' application, no input is sent and no hotkey is registered\naliases: 'validate'")
+        .arg(Arg::new("path")
+            .index(1)
+            .required(true)
+            .help("path of the application folder")
+            .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(PathBuf))
+        )
+}
+
+#[instrument(name = "check-subcommand", skip_all)]
+pub(super) fn process(arg_matches: &ArgMatches) -> anyhow::Result<()> {
+    let (folder, input, context) = load_application(arg_matches, None)?;
+
+    let parsed = parse(&folder, &input, &context)?;
+
+    let problems = Engine::validate(&parsed);
+    for problem in problems.iter() {
+        tracing::error!("{}", problem);
+    }
+
+    if problems.is_empty() {
+        tracing::info!("No problems found");
+        println!("No problems found");
+        Ok(())
+    } else {
+        Err(anyhow!("Check failed, {} problem(s) found", problems.len()))
+    }
+}
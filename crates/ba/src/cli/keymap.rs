@@ -0,0 +1,117 @@
+use crate::{
+    compiler::Button,
+    keymap::KeyMap,
+    lint::{Level, lint_maps},
+    mousemap::MouseMap,
+};
+
+use super::*;
+
+pub(super) fn subcommand() -> Command {
+    Command::new("keymap")
+        .about("Inspects the key and mouse button names of a BA application")
+        .long_about("This subcommand is used to inspect the names a 'Beaulieu Automation' application can use, they are looked up in the application's maps, then in the user's maps, then in the built-in ones")
+        .subcommand_required(true)
+        .subcommand(Command::new("which")
+            .about("Shows which key or button a name refers to and where it is defined")
+            .arg(Arg::new("path")
+                .index(1)
+                .required(true)
+                .help("path of the application folder")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(PathBuf))
+            )
+            .arg(Arg::new("name")
+                .index(2)
+                .required(true)
+                .help("name of a key or mouse button, e.g. 'esc'")
+                .action(ArgAction::Set)
+            )
+        )
+        .subcommand(Command::new("lint")
+            .about("Checks the keymap.json and mousemap.json of the application for names that cannot work as intended")
+            .arg(Arg::new("path")
+                .index(1)
+                .required(true)
+                .help("path of the application folder")
+                .action(ArgAction::Set)
+                .value_parser(clap::value_parser!(PathBuf))
+            )
+        )
+}
+
+#[instrument(name = "keymap-subcommand", skip_all)]
+pub(super) fn process(arg_matches: &ArgMatches) -> anyhow::Result<()> {
+    match arg_matches.subcommand() {
+        Some(("which", arg_matches)) => which(arg_matches),
+        Some(("lint", arg_matches)) => lint(arg_matches),
+        _ => Ok(()),
+    }
+}
+
+fn which(arg_matches: &ArgMatches) -> anyhow::Result<()> {
+    let folder = application_folder(arg_matches)?;
+    let context = load_context(&folder, None)?;
+    let name = arg_matches
+        .get_one::<String>("name")
+        .ok_or(anyhow!("Failed to extract a valid name"))?;
+
+    let (button, found) = context.resolve(name).ok_or(anyhow!(
+        "No key or mouse button found associated with '{}'",
+        name.to_lowercase()
+    ))?;
+    let filename = match button {
+        Button::K(_) => "keymap.json",
+        Button::M(_) => "mousemap.json",
+    };
+    match &found.folder {
+        Some(folder) => println!(
+            "'{}' → {}, from the {} {} '{}'",
+            name.to_lowercase(),
+            button,
+            found.layer,
+            filename,
+            folder.join(filename).display()
+        ),
+        None => println!(
+            "'{}' → {}, from the {} names",
+            name.to_lowercase(),
+            button,
+            found.layer
+        ),
+    }
+    Ok(())
+}
+
+fn lint(arg_matches: &ArgMatches) -> anyhow::Result<()> {
+    let folder = application_folder(arg_matches)?;
+    let keys = KeyMap::read_entries(&folder.join("keymap.json"))?;
+    let buttons = MouseMap::read_entries(&folder.join("mousemap.json"))?;
+
+    let findings = lint_maps(&keys, &buttons);
+    for finding in findings.iter() {
+        match finding.level {
+            Level::Error => tracing::error!("{}", finding),
+            Level::Warning => tracing::warn!("{}", finding),
+        }
+        println!("{}", finding);
+    }
+
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.level == Level::Error)
+        .count();
+    if findings.is_empty() {
+        println!("No problems found");
+        Ok(())
+    } else if errors == 0 {
+        println!("{} warning(s) found", findings.len());
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Lint failed, {} error(s) and {} warning(s) found",
+            errors,
+            findings.len() - errors
+        ))
+    }
+}
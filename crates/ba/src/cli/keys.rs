@@ -0,0 +1,121 @@
+use crate::compiler::Button;
+use inputbot::{KeybdKey, MouseButton};
+
+use super::*;
+
+pub(super) fn subcommand() -> Command {
+    Command::new("keys")
+        .about("Lists the key and mouse button names usable in scripts")
+        .long_about("This subcommand is used to list the names a 'Beaulieu Automation' application can use, with the key or mouse button they refer to, its virtual-key code and the file defining them\nwithout a path, only the names of the user and the built-in ones are listed\nthe built-in names include the ones of the keyboard layout defined by KEYBOARD_LAYOUT in main.ba, otherwise of ch-fr")
+        .arg(Arg::new("path")
+            .index(1)
+            .help("path of the application folder")
+            .action(ArgAction::Set)
+            .value_parser(clap::value_parser!(PathBuf))
+        )
+        .arg(Arg::new("search")
+            .short('s')
+            .long("search")
+            .help("only lists the names, or keys and buttons, containing the given text, e.g. 'arrow'")
+            .action(ArgAction::Set)
+        )
+        .arg(Arg::new("for")
+            .short('f')
+            .long("for")
+            .help("only lists the names of the given key or mouse button, e.g. 'EscapeKey'")
+            .action(ArgAction::Set)
+        )
+}
+
+#[instrument(name = "keys-subcommand", skip_all)]
+pub(super) fn process(arg_matches: &ArgMatches) -> anyhow::Result<()> {
+    let context = match arg_matches.contains_id("path") {
+        true => load_context(&application_folder(arg_matches)?, None)?,
+        false => load_user_context(select_names_layout(None, None))?,
+    };
+    let search = arg_matches
+        .get_one::<String>("search")
+        .map(|search| search.to_lowercase());
+    let target = arg_matches
+        .get_one::<String>("for")
+        .map(|target| parse_button(target))
+        .transpose()?;
+
+    let rows: Vec<[String; 4]> = context
+        .names()
+        .into_iter()
+        .filter(|(_, button, _)| target.is_none_or(|target| target == *button))
+        .filter(|(name, button, _)| {
+            search.as_ref().is_none_or(|search| {
+                name.contains(search.as_str())
+                    || button.to_string().to_lowercase().contains(search.as_str())
+            })
+        })
+        .map(|(name, button, found)| {
+            let code = match button {
+                Button::K(key) => u64::from(key),
+                Button::M(button) => u32::from(button) as u64,
+            };
+            let source = match (&found.folder, button) {
+                (Some(folder), Button::K(_)) => folder.join("keymap.json").display().to_string(),
+                (Some(folder), Button::M(_)) => folder.join("mousemap.json").display().to_string(),
+                (None, _) => found.layer.to_string(),
+            };
+            [
+                name.to_string(),
+                button.to_string(),
+                format!("0x{:02X}", code),
+                source,
+            ]
+        })
+        .collect();
+
+    if rows.is_empty() {
+        match target {
+            Some(target) => println!("No name refers to {}", target),
+            None => println!("No names found"),
+        }
+        return Ok(());
+    }
+
+    let header = [
+        "NAME".to_string(),
+        "BUTTON".to_string(),
+        "VK".to_string(),
+        "FROM".to_string(),
+    ];
+    let mut widths = [0; 3];
+    for row in std::iter::once(&header).chain(rows.iter()) {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for [name, button, code, source] in std::iter::once(&header).chain(rows.iter()) {
+        println!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+            name,
+            button,
+            code,
+            source,
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+        );
+    }
+    println!("{} name(s)", rows.len());
+    Ok(())
+}
+
+/// Key or mouse button written as in keymap.json and mousemap.json, e.g. 'EscapeKey'
+fn parse_button(name: &str) -> anyhow::Result<Button> {
+    let value = serde_json::Value::String(name.to_string());
+    serde_json::from_value::<KeybdKey>(value.clone())
+        .map(Button::K)
+        .or_else(|_| serde_json::from_value::<MouseButton>(value).map(Button::M))
+        .map_err(|_| {
+            anyhow!(
+                "'{}' is neither a key nor a mouse button, e.g. 'EscapeKey' or 'LeftButton'",
+                name
+            )
+        })
+}
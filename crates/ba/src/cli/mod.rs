@@ -1,21 +1,175 @@
-use anyhow::anyhow;
+use crate::{
+    compiler::{CompileContext, Expression, Layer, Parser},
+    keymap::KeyMap,
+    mousemap::MouseMap,
+};
+use anyhow::{Context, anyhow};
 use clap::{Arg, ArgAction, ArgMatches, Command, command};
+use inputbot::{InputBackend, KeyboardLayout};
+use std::{
+    io::Read,
+    path::{Path, PathBuf, absolute},
+    sync::Arc,
+};
 use tracing::instrument;
 
+mod check;
+mod keymap;
+mod keys;
 mod new;
 mod run;
 
-pub fn cli(resolution: (i32, i32)) -> anyhow::Result<()> {
+pub fn cli() -> anyhow::Result<()> {
     let command = command!()
         .subcommand(new::subcommand())
-        .subcommand(run::subcommand());
+        .subcommand(run::subcommand())
+        .subcommand(check::subcommand())
+        .subcommand(keymap::subcommand())
+        .subcommand(keys::subcommand());
 
     let arg_matches = command.get_matches();
 
     match arg_matches.subcommand() {
-        Some(("new", arg_matches)) => new::process(arg_matches, resolution),
-        Some(("run", arg_matches)) => run::process(arg_matches, resolution),
+        Some(("new", arg_matches)) => new::process(arg_matches),
+        Some(("run", arg_matches)) => run::process(arg_matches),
+        Some(("check", arg_matches)) => check::process(arg_matches),
+        Some(("keymap", arg_matches)) => keymap::process(arg_matches),
+        Some(("keys", arg_matches)) => keys::process(arg_matches),
         Some(_) => Ok(()),
         None => Ok(()),
     }
 }
+
+/// Creates the input backend of the host platform
+fn init_backend() -> anyhow::Result<Arc<dyn InputBackend>> {
+    let backend = inputbot::default_backend()?;
+    tracing::info!("Using the '{}' input backend", backend.name());
+    Ok(backend)
+}
+
+/// Gets the width and height of the primary monitor
+fn host_resolution(backend: &dyn InputBackend) -> anyhow::Result<(i32, i32)> {
+    let (width, height) = backend.primary_monitor_size()?;
+    tracing::info!("Primary monitor detected - {width}x{height}");
+    Ok((width, height))
+}
+
+/// Parses a main file and the files it includes from the application folder,
+/// logging every rendered diagnostic on failure
+fn parse(folder: &Path, input: &str, context: &CompileContext) -> anyhow::Result<Vec<Expression>> {
+    let mut parser = Parser::new(input, context).with_root(folder);
+    parser.process().map_err(|diagnostics| {
+        for diagnostic in diagnostics.iter() {
+            tracing::error!("\n{}", diagnostic.render(parser.sources()));
+        }
+        anyhow!("Parsing failed, {} problem(s) found", diagnostics.len())
+    })
+}
+
+/// Loads the names and the main file of the application folder given as the "path" argument,
+/// then returns the folder, the content of its main file and the context to compile it with,
+/// `detected` stands in for a missing KEYBOARD_LAYOUT
+fn load_application(
+    arg_matches: &ArgMatches,
+    detected: Option<&'static KeyboardLayout>,
+) -> anyhow::Result<(PathBuf, String, CompileContext)> {
+    let absolute_path = application_folder(arg_matches)?;
+    let context = load_context(&absolute_path, detected)?;
+
+    // load main file
+    let main_filepath = absolute_path.join("main.ba");
+    let mut input: String = String::new();
+    std::fs::OpenOptions::new()
+        .read(true)
+        .open(&main_filepath)
+        .context(format!(
+            "Failed to read/open file with path '{}'",
+            main_filepath.display()
+        ))?
+        .read_to_string(&mut input)?;
+
+    Ok((absolute_path, input, context))
+}
+
+/// Absolute path of the application folder given as the "path" argument
+fn application_folder(arg_matches: &ArgMatches) -> anyhow::Result<PathBuf> {
+    let path = arg_matches
+        .get_one::<PathBuf>("path")
+        .ok_or(anyhow!("Failed to extract a valid path/name"))?;
+
+    let absolute_path = absolute(path)
+        .map_err(|err| {
+            tracing::warn!(
+                "Failed to absolutize given path '{}' due to '{}'",
+                path.display(),
+                err
+            );
+            err
+        })
+        .unwrap_or(path.clone());
+
+    if !path.is_dir() {
+        Err(anyhow!("Specified path/name does not exist"))?;
+    } else if path.extension().is_some() {
+        Err(anyhow!("Path required, got filepath instead"))?;
+    }
+    Ok(absolute_path)
+}
+
+/// Names of the application folder, then of the user's configuration folder, then the built-in ones
+/// completed by the ones of the layout of the main file, the maps of the user are optional
+fn load_context(
+    folder: &Path,
+    detected: Option<&'static KeyboardLayout>,
+) -> anyhow::Result<CompileContext> {
+    let main = std::fs::read_to_string(folder.join("main.ba")).ok();
+    let context = load_user_context(select_names_layout(main.as_deref(), detected))?;
+
+    // load KeyMap
+    let keymap = KeyMap::load(&folder.join("keymap.json"))?;
+    tracing::debug!("KeyMap loaded");
+
+    // load MouseMap
+    let mousemap = MouseMap::load(&folder.join("mousemap.json"))?;
+    tracing::debug!("MouseMap loaded");
+
+    Ok(context.with_layer(Layer::App, Some(folder), keymap, mousemap))
+}
+
+/// Layout whose names complete the built-in ones, the one defined by the KEYBOARD_LAYOUT
+/// of the main file, otherwise the detected one if the user asked for it, otherwise Swiss French,
+/// the host is never queried here so that names resolve the same on every machine
+fn select_names_layout(
+    main: Option<&str>,
+    detected: Option<&'static KeyboardLayout>,
+) -> &'static KeyboardLayout {
+    main.and_then(Parser::keyboard_layout_of)
+        .or(detected)
+        .unwrap_or(inputbot::SWISS_FRENCH)
+}
+
+/// Names of the user's configuration folder, if any, then the built-in ones
+/// completed by the names of `layout`
+fn load_user_context(layout: &KeyboardLayout) -> anyhow::Result<CompileContext> {
+    let mut context = CompileContext::default().with_keyboard_layout(layout);
+
+    // load user maps
+    let user_folder = crate::dirs::Dirs::get().config_dir();
+    let keymap_filepath = user_folder.join("keymap.json");
+    let mousemap_filepath = user_folder.join("mousemap.json");
+    if keymap_filepath.exists() || mousemap_filepath.exists() {
+        let keymap = match keymap_filepath.exists() {
+            true => KeyMap::load(&keymap_filepath)?,
+            false => KeyMap::from(Vec::new()),
+        };
+        let mousemap = match mousemap_filepath.exists() {
+            true => MouseMap::load(&mousemap_filepath)?,
+            false => MouseMap::from(Vec::new()),
+        };
+        context = context.with_layer(Layer::User, Some(user_folder), keymap, mousemap);
+        tracing::debug!("User maps loaded from '{}'", user_folder.display());
+    } else {
+        tracing::debug!("No user maps in '{}'", user_folder.display());
+    }
+    Ok(context)
+}
@@ -1,6 +1,7 @@
 use anyhow::Context;
 
-use crate::{keymap::DEFAULT_KEYMAP, mousemap::DEFAULT_MOUSEMAP};
+use crate::{keymap::KeyMap, mousemap::DEFAULT_MOUSEMAP};
+use inputbot::KeyboardLayout;
 
 use super::*;
 use std::{
@@ -22,10 +23,78 @@ pub(super) fn subcommand() -> Command {
                 .action(ArgAction::Set)
                 .value_parser(clap::value_parser!(PathBuf))
             )
+        .arg(Arg::new("layout")
+                .long("layout")
+                .required(false)
+                .help("keyboard layout the application is written for, detected if omitted")
+                .long_help(format!("keyboard layout the application is written for, detected if omitted\none of {}", layout_names()))
+                .action(ArgAction::Set)
+            )
+}
+
+fn layout_names() -> String {
+    KeyboardLayout::all()
+        .iter()
+        .map(|layout| layout.name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// written to main.ba when the primary monitor cannot be queried
+const PLACEHOLDER_RESOLUTION: (i32, i32) = (1920, 1080);
+
+/// Resolution of the primary monitor, a placeholder if there is no backend to query it
+fn select_resolution(backend: Option<&dyn InputBackend>) -> (i32, i32) {
+    match backend.map(host_resolution) {
+        Some(Ok(resolution)) => resolution,
+        failed => {
+            if let Some(Err(err)) = failed {
+                tracing::warn!("Failed to query the primary monitor, '{:#}'", err);
+            }
+            tracing::warn!(
+                "Defaulting to a resolution of {}x{}, set RESOLUTION in main.ba to the one of the target monitor",
+                PLACEHOLDER_RESOLUTION.0,
+                PLACEHOLDER_RESOLUTION.1
+            );
+            PLACEHOLDER_RESOLUTION
+        }
+    }
+}
+
+/// Layout given as the "layout" argument, otherwise the active one
+fn select_layout(
+    arg_matches: &ArgMatches,
+    backend: Option<&dyn InputBackend>,
+) -> anyhow::Result<&'static KeyboardLayout> {
+    if let Some(name) = arg_matches.get_one::<String>("layout") {
+        return KeyboardLayout::by_name(name).ok_or(anyhow!(
+            "Unknown keyboard layout '{}', expected one of {}",
+            name,
+            layout_names()
+        ));
+    }
+    match backend
+        .map(|backend| backend.keyboard_layout())
+        .transpose()?
+        .flatten()
+    {
+        Some(id) => KeyboardLayout::by_id(&id).ok_or(anyhow!(
+            "No profile for the active keyboard layout '{}', select one of {} with --layout",
+            id,
+            layout_names()
+        )),
+        None => {
+            tracing::warn!(
+                "Failed to detect the keyboard layout, defaulting to {}",
+                inputbot::SWISS_FRENCH
+            );
+            Ok(inputbot::SWISS_FRENCH)
+        }
+    }
 }
 
 #[instrument(name = "new-subcommand", skip_all)]
-pub(super) fn process(arg_matches: &ArgMatches, resolution: (i32, i32)) -> anyhow::Result<()> {
+pub(super) fn process(arg_matches: &ArgMatches) -> anyhow::Result<()> {
     let path = arg_matches
         .get_one::<PathBuf>("path")
         .ok_or(anyhow!("Failed to extract a valid path/name"))?;
@@ -47,6 +116,14 @@ pub(super) fn process(arg_matches: &ArgMatches, resolution: (i32, i32)) -> anyho
         Err(anyhow!("Path required, got filepath instead"))?;
     }
 
+    // only used to query the host, e.g. there is no display over SSH
+    let backend = init_backend()
+        .inspect_err(|err| tracing::warn!("No input backend available, '{:#}'", err))
+        .ok();
+    let resolution = select_resolution(backend.as_deref());
+    let layout = select_layout(arg_matches, backend.as_deref())?;
+    tracing::info!("Using the {} keyboard layout", layout);
+
     tracing::info!(
         "Attempting to create new application at absolute path '{}'",
         absolute_path.display()
@@ -70,7 +147,7 @@ pub(super) fn process(arg_matches: &ArgMatches, resolution: (i32, i32)) -> anyho
         .open(&main_filepath)
         .context(format!("Failed to create main file with path '{}'", &main_filepath.display()))?
         .write_all(
-            format!("// Resolution of the primary monitor for which this script was created\n// DO NOT MODIFY\ndefine RESOLUTION = {}, {}\n\n// The standard delay between actions given in milliseconds\ndefine DELAY_BETWEEN_ACTIONS = 50\n\n// The button used to stop the application if required\ndefine GLOBAL_HALT_KEY = Esc", resolution.0, resolution.1)
+            format!("// Resolution of the primary monitor for which this script was created\n// DO NOT MODIFY\ndefine RESOLUTION = {}, {}\n\n// The standard delay between actions given in milliseconds\ndefine DELAY_BETWEEN_ACTIONS = 50\n\n// The button used to stop the application if required\ndefine GLOBAL_HALT_KEY = Esc\n\n// The keyboard layout the key names are written for\ndefine KEYBOARD_LAYOUT = \"{}\"", resolution.0, resolution.1, layout.name)
                     .as_bytes()
         )?;
     tracing::debug!("Created main.ba");
@@ -98,7 +175,7 @@ pub(super) fn process(arg_matches: &ArgMatches, resolution: (i32, i32)) -> anyho
             "Failed to create keymap file with path '{}'",
             &keymap_filepath.display()
         ))?
-        .write_all(&serde_json::to_vec_pretty(&DEFAULT_KEYMAP[..]).unwrap())?;
+        .write_all(&serde_json::to_vec_pretty(&KeyMap::defaults_for(layout)).unwrap())?;
     tracing::debug!("Created keymap.json");
 
     // generate mousemap
@@ -1,12 +1,7 @@
-use anyhow::Context;
-
 use crate::compiler::Engine;
+use inputbot::{KeyboardLayout, RealClock};
 
 use super::*;
-use std::{
-    io::Read,
-    path::{PathBuf, absolute},
-};
 
 pub(super) fn subcommand() -> Command {
     Command::new("run")
@@ -27,61 +22,47 @@ pub(super) fn subcommand() -> Command {
             .action(ArgAction::Set)
             .value_parser(clap::value_parser!(usize))
         )
+        .arg(Arg::new("detect-layout")
+            .long("detect-layout")
+            .help("runs an application without KEYBOARD_LAYOUT for the active keyboard layout instead of ch-fr")
+            .action(ArgAction::SetTrue)
+        )
 }
 
-#[instrument(name = "run-subcommand", skip_all)]
-pub(super) fn process(arg_matches: &ArgMatches, resolution: (i32, i32)) -> anyhow::Result<()> {
-    let path = arg_matches
-        .get_one::<PathBuf>("path")
-        .ok_or(anyhow!("Failed to extract a valid path/name"))?;
-
-    let absolute_path = absolute(path)
-        .map_err(|err| {
-            tracing::warn!(
-                "Failed to absolutize given path '{}' due to '{}'",
-                path.display(),
-                err
-            );
-            err
-        })
-        .unwrap_or(path.clone());
-
-    if !path.is_dir() {
-        Err(anyhow!("Specified path/name does not exist"))?;
-    } else if path.extension().is_some() {
-        Err(anyhow!("Path required, got filepath instead"))?;
-    }
+/// Profile of the active keyboard layout, only queried when asked for with --detect-layout
+fn detect_layout(backend: &dyn InputBackend) -> anyhow::Result<&'static KeyboardLayout> {
+    let id = backend
+        .keyboard_layout()?
+        .ok_or(anyhow!("Failed to detect the keyboard layout"))?;
+    let layout = KeyboardLayout::by_id(&id).ok_or(anyhow!(
+        "No profile for the active keyboard layout '{}'",
+        id
+    ))?;
+    tracing::info!("Detected the {} keyboard layout", layout);
+    Ok(layout)
+}
 
+#[instrument(name = "run-subcommand", skip_all)]
+pub(super) fn process(arg_matches: &ArgMatches) -> anyhow::Result<()> {
     let nb_cycles = *arg_matches.get_one::<usize>("repetitions").unwrap_or(&1);
+    let backend = init_backend()?;
+    let detected = match arg_matches.get_flag("detect-layout") {
+        true => Some(detect_layout(backend.as_ref())?),
+        false => None,
+    };
+    let (folder, input, context) = load_application(arg_matches, detected)?;
 
-    // filepaths
-    let main_filepath = absolute_path.join("main.ba");
-    let keymap_filepath = absolute_path.join("keymap.json");
-    let mousemap_filepath = absolute_path.join("mousemap.json");
-
-    // load KeyMap
-    crate::keymap::KeyMap::init(&keymap_filepath)?;
-    tracing::debug!("KeyMap initialized");
-
-    // load MouseMap
-    crate::mousemap::MouseMap::init(&mousemap_filepath)?;
-    tracing::debug!("MouseMap initialized");
-
-    // load main file
-    let mut input: String = String::new();
-    std::fs::OpenOptions::new()
-        .read(true)
-        .open(&main_filepath)
-        .context(format!(
-            "Failed to read/open file with path '{}'",
-            main_filepath.display()
-        ))?
-        .read_to_string(&mut input)?;
-
-    let mut parser = crate::compiler::Parser::new(&input);
-    let parsed = parser.process()?;
+    let mut parsed = parse(&folder, &input, &context)?;
+    // the detected layout stands in for a missing definition
+    if !parsed
+        .iter()
+        .any(|expr| matches!(expr, Expression::KeyboardLayout(_)))
+    {
+        parsed.extend(detected.map(Expression::KeyboardLayout));
+    }
 
-    let engine = Engine::new(parsed, resolution)?;
+    let resolution = host_resolution(backend.as_ref())?;
+    let engine = Engine::new(parsed, resolution, backend, Arc::new(RealClock::new()))?;
     engine.start(nb_cycles)?;
 
     Ok(())
@@ -1,4 +1,7 @@
-use inputbot::{KeybdKey, MouseButton};
+use inputbot::{HotkeyHandle, InputBackend, KeybdKey, Modifiers, MouseButton};
+use std::sync::{Mutex, mpsc};
+
+use super::engine::{Cancelled, Runtime};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Button {
@@ -6,75 +9,190 @@ pub enum Button {
     M(MouseButton),
 }
 
-impl TryFrom<&str> for Button {
-    type Error = anyhow::Error;
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let value = value.to_lowercase();
-        if let Some(key) = crate::keymap::KeyMap::get().get(&value) {
-            Ok(Button::K(*key))
-        } else if let Some(button) = crate::mousemap::MouseMap::get().get(&value) {
-            Ok(Button::M(*button))
-        } else {
-            Err(anyhow::anyhow!(
-                "No key or mouse button found associated with '{}'",
-                value
-            ))
+impl std::fmt::Display for Button {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Button::K(key) => write!(f, "{:?}", key),
+            Button::M(button) => write!(f, "{:?}", button),
+        }
+    }
+}
+
+/// Buttons joined with `+`, e.g. `ctrl+shift+s`, pressed in order and released in reverse order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chord(pub Vec<Button>);
+
+impl From<Button> for Chord {
+    fn from(button: Button) -> Self {
+        Chord(vec![button])
+    }
+}
+
+impl Chord {
+    pub(super) fn tap(&self, backend: &dyn InputBackend) {
+        match self.0.as_slice() {
+            [button] => button.tap(backend),
+            buttons => {
+                let keys: Option<Vec<KeybdKey>> = buttons
+                    .iter()
+                    .map(|button| match button {
+                        Button::K(key) => Some(*key),
+                        Button::M(_) => None,
+                    })
+                    .collect();
+                match keys {
+                    // sent at once, the keys cannot remain stuck down
+                    Some(keys) => backend.key_chord(&keys),
+                    None => {
+                        self.0.iter().for_each(|button| button.press(backend));
+                        self.0
+                            .iter()
+                            .rev()
+                            .for_each(|button| button.release(backend));
+                    }
+                }
+            }
         }
     }
+    /// Buttons stay held until released, the runtime keeps track of them
+    pub(super) fn press(&self, runtime: &Runtime) -> Result<(), Cancelled> {
+        self.0.iter().try_for_each(|button| runtime.press(*button))
+    }
+    pub(super) fn release(&self, runtime: &Runtime) {
+        self.0
+            .iter()
+            .rev()
+            .for_each(|button| runtime.release(*button));
+    }
 }
 
 impl Button {
-    pub(super) fn tap(&self) {
+    pub(super) fn tap(&self, backend: &dyn InputBackend) {
         match self {
-            Self::K(a) => a.tap(),
-            Self::M(a) => a.tap(),
+            Self::K(a) => backend.key_tap(*a),
+            Self::M(a) => backend.mouse_tap(*a),
         }
     }
-    pub(super) fn press(&self) {
+    pub(super) fn is_pressed(&self, backend: &dyn InputBackend) -> bool {
         match self {
-            Self::K(a) => a.press(),
-            Self::M(a) => a.press(),
+            Self::K(a) => backend.key_is_pressed(*a),
+            Self::M(a) => backend.mouse_is_pressed(*a),
         }
     }
-    pub(super) fn release(&self) {
+    pub(super) fn press(&self, backend: &dyn InputBackend) {
         match self {
-            Self::K(a) => a.release(),
-            Self::M(a) => a.release(),
+            Self::K(a) => backend.key_press(*a),
+            Self::M(a) => backend.mouse_press(*a),
         }
     }
+    pub(super) fn release(&self, backend: &dyn InputBackend) {
+        match self {
+            Self::K(a) => backend.key_release(*a),
+            Self::M(a) => backend.mouse_release(*a),
+        }
+    }
+}
+
+/// Button registered as a hotkey, triggered only while its modifiers are held down,
+/// e.g. `ctrl+alt+nr1`. Mouse buttons are observed through a low-level hook and cannot
+/// be combined with modifiers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hotkey {
+    pub modifiers: Modifiers,
+    pub button: Button,
+    /// the click still reaches the other applications, only used by mouse buttons
+    pub passthrough: bool,
+}
+
+impl From<Button> for Hotkey {
+    fn from(button: Button) -> Self {
+        Hotkey {
+            modifiers: Modifiers::default(),
+            button,
+            passthrough: false,
+        }
+    }
+}
+
+impl std::fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.modifiers.is_empty() {
+            write!(f, "{}+", self.modifiers)?;
+        }
+        write!(f, "{}", self.button)
+    }
+}
+
+impl Hotkey {
+    /// Whether both hotkeys are triggered by the same input, regardless of `passthrough`
+    pub(super) fn overlaps(&self, other: &Hotkey) -> bool {
+        self.modifiers == other.modifiers && self.button == other.button
+    }
     pub(super) fn listen_once<F: FnOnce() + Send + 'static>(
         self,
+        backend: &dyn InputBackend,
         callback: F,
-    ) -> anyhow::Result<std::thread::JoinHandle<()>> {
-        match self {
-            Self::K(key) => Ok(key.listen_once(callback)?),
-            Self::M(_) => {
-                tracing::error!("Mouse buttons cannot be bound, use keys instead");
-                Err(anyhow::anyhow!("Cannot bind mouse button"))
+    ) -> anyhow::Result<HotkeyHandle> {
+        match self.button {
+            Button::K(key) => Ok(backend.listen_once(
+                inputbot::Hotkey::new(self.modifiers, key),
+                Box::new(callback),
+            )?),
+            Button::M(button) => {
+                // the hook fires on every press, only the first one is kept
+                let callback = Mutex::new(Some(callback));
+                Ok(backend.hook_mouse(
+                    button,
+                    !self.passthrough,
+                    Box::new(move || {
+                        let callback = callback
+                            .lock()
+                            .unwrap_or_else(|err| err.into_inner())
+                            .take();
+                        if let Some(callback) = callback {
+                            callback()
+                        }
+                    }),
+                )?)
             }
         }
     }
-    pub(super) fn await_in_place(self) -> anyhow::Result<()> {
-        match self {
-            Self::K(key) => key.await_in_place(),
-            Self::M(_) => {
-                tracing::error!("Mouse buttons cannot be bound, use keys instead");
-                Err(anyhow::anyhow!("Cannot bind mouse button"))
+    /// Blocks until the hotkey is pressed or the run is cancelled, a hotkey that
+    /// cannot be registered is not awaited
+    pub(super) fn await_in_place(self, runtime: &Runtime) -> Result<(), Cancelled> {
+        let (sender, receiver) = mpsc::channel();
+        let _handle = match self.listen_once(runtime.backend.as_ref(), move || {
+            let _ = sender.send(());
+        }) {
+            Ok(handle) => handle,
+            Err(err) => {
+                tracing::warn!("Failed to await '{}', {}", self, err);
+                return Ok(());
+            }
+        };
+        loop {
+            match receiver.try_recv() {
+                Ok(()) => return Ok(()),
+                Err(mpsc::TryRecvError::Empty) => runtime.poll()?,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    tracing::warn!("Stopped awaiting '{}', its listener went away", self);
+                    return Ok(());
+                }
             }
         }
     }
     pub(super) fn detached_hotkey<F: Fn() + Send + 'static>(
         self,
+        backend: &dyn InputBackend,
         callback: F,
-    ) -> anyhow::Result<()> {
-        match self {
-            Self::K(key) => {
-                key.detached_hotkey(callback)?;
-                Ok(())
-            }
-            Self::M(_) => {
-                tracing::error!("Mouse buttons cannot be bound, use keys instead");
-                Err(anyhow::anyhow!("Cannot bind mouse button"))
+    ) -> anyhow::Result<HotkeyHandle> {
+        match self.button {
+            Button::K(key) => Ok(backend.detached_hotkey(
+                inputbot::Hotkey::new(self.modifiers, key),
+                Box::new(callback),
+            )?),
+            Button::M(button) => {
+                Ok(backend.hook_mouse(button, !self.passthrough, Box::new(callback))?)
             }
         }
     }
@@ -0,0 +1,79 @@
+use super::{button::Button, engine::Runtime, value::Term};
+use inputbot::KeybdKey;
+use regex::Regex;
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+impl Comparison {
+    fn holds(&self, ordering: Ordering) -> bool {
+        match self {
+            Self::Eq => ordering.is_eq(),
+            Self::NotEq => ordering.is_ne(),
+            Self::Lt => ordering.is_lt(),
+            Self::LtEq => ordering.is_le(),
+            Self::Gt => ordering.is_gt(),
+            Self::GtEq => ordering.is_ge(),
+        }
+    }
+}
+
+/// A regex compiled at parse time
+#[derive(Debug, Clone)]
+pub struct Pattern(pub Regex);
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+/// What an `If` tests, checked when the `If` is executed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Pressed(Button),
+    Toggled(KeybdKey),
+    /// the clipboard content compared with the term as a string
+    Clipboard(Comparison, Term),
+    ClipboardMatches(Pattern),
+    Compare(Term, Comparison, Term),
+}
+
+impl Condition {
+    pub(super) fn evaluate(&self, runtime: &Runtime) -> anyhow::Result<bool> {
+        let backend = runtime.backend.as_ref();
+        match self {
+            Self::Pressed(button) => Ok(button.is_pressed(backend)),
+            Self::Toggled(key) => Ok(backend.key_is_toggled(*key)),
+            Self::Clipboard(comparison, term) => {
+                let expected = runtime.evaluate(term)?.to_string();
+                Ok(clipboard(runtime)
+                    .is_some_and(|content| comparison.holds(content.as_str().cmp(&expected))))
+            }
+            Self::ClipboardMatches(pattern) => {
+                Ok(clipboard(runtime).is_some_and(|content| pattern.0.is_match(&content)))
+            }
+            Self::Compare(lhs, comparison, rhs) => {
+                let ordering = runtime.evaluate(lhs)?.compare(&runtime.evaluate(rhs)?)?;
+                Ok(comparison.holds(ordering))
+            }
+        }
+    }
+}
+
+// an unreadable clipboard fails every test on its content
+fn clipboard(runtime: &Runtime) -> Option<String> {
+    let content = runtime.backend.get_clipboard_string();
+    if content.is_none() {
+        tracing::warn!("Failed to copy the clipboard's content");
+    }
+    content
+}
@@ -0,0 +1,274 @@
+use super::button::Button;
+use crate::{keymap::KeyMap, mousemap::MouseMap};
+use inputbot::KeyboardLayout;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+/// Where the names of a keymap and mousemap pair are defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    /// keymap.json and mousemap.json of the application folder
+    App,
+    /// keymap.json and mousemap.json of the user's configuration folder
+    User,
+    /// defaults compiled into ba
+    BuiltIn,
+}
+
+impl std::fmt::Display for Layer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layer::App => write!(f, "application"),
+            Layer::User => write!(f, "user"),
+            Layer::BuiltIn => write!(f, "built-in"),
+        }
+    }
+}
+
+/// A keymap and mousemap pair of a layer, with the folder they were read from
+#[derive(Debug)]
+pub struct NameLayer {
+    pub layer: Layer,
+    pub folder: Option<PathBuf>,
+    pub keymap: KeyMap,
+    pub mousemap: MouseMap,
+}
+
+/// Names the parser resolves buttons with, given to every parser of a compilation
+/// so that several scripts can be compiled with different maps
+#[derive(Debug)]
+pub struct CompileContext {
+    // looked up in order, the first layer defining a name wins
+    layers: Vec<NameLayer>,
+}
+
+impl Default for CompileContext {
+    /// Only the built-in names, without the ones depending on the keyboard layout
+    fn default() -> Self {
+        Self {
+            layers: vec![NameLayer {
+                layer: Layer::BuiltIn,
+                folder: None,
+                keymap: KeyMap::default(),
+                mousemap: MouseMap::default(),
+            }],
+        }
+    }
+}
+
+impl CompileContext {
+    /// Completes the built-in names with the names of the given layout, as written by `ba new`
+    pub fn with_keyboard_layout(mut self, layout: &KeyboardLayout) -> Self {
+        for name_layer in self.layers.iter_mut() {
+            if name_layer.layer == Layer::BuiltIn {
+                name_layer.keymap = KeyMap::from(&KeyMap::defaults_for(layout)[..]);
+            }
+        }
+        self
+    }
+
+    /// Adds a layer looked up before the ones already added
+    pub fn with_layer(
+        mut self,
+        layer: Layer,
+        folder: Option<&Path>,
+        keymap: KeyMap,
+        mousemap: MouseMap,
+    ) -> Self {
+        self.layers.insert(
+            0,
+            NameLayer {
+                layer,
+                folder: folder.map(Path::to_path_buf),
+                keymap,
+                mousemap,
+            },
+        );
+        self
+    }
+
+    /// Key or mouse button of the given name with the layer it was found in,
+    /// within a layer the keymap is looked up first
+    pub fn resolve(&self, name: &str) -> Option<(Button, &NameLayer)> {
+        let name = name.to_lowercase();
+        self.layers.iter().find_map(|layer| {
+            if let Some(key) = layer.keymap.get(&name) {
+                Some((Button::K(*key), layer))
+            } else {
+                layer
+                    .mousemap
+                    .get(&name)
+                    .map(|button| (Button::M(*button), layer))
+            }
+        })
+    }
+
+    /// Every name a script can use with its key or mouse button and the layer it is found in,
+    /// sorted by name, names hidden by an earlier layer are left out
+    pub fn names(&self) -> Vec<(&str, Button, &NameLayer)> {
+        let mut seen = HashSet::new();
+        let mut names = Vec::new();
+        for layer in self.layers.iter() {
+            let keys = layer
+                .keymap
+                .iter()
+                .map(|(name, key)| (name.as_str(), Button::K(*key)));
+            let buttons = layer
+                .mousemap
+                .iter()
+                .map(|(name, button)| (name.as_str(), Button::M(*button)));
+            for (name, button) in keys.chain(buttons) {
+                // names are looked up in lowercase, the others can never be found
+                if name.to_lowercase() == name && seen.insert(name) {
+                    names.push((name, button, layer));
+                }
+            }
+        }
+        names.sort_by_key(|(name, ..)| *name);
+        names
+    }
+
+    /// Key or mouse button of the given name
+    pub fn button(&self, name: &str) -> anyhow::Result<Button> {
+        self.resolve(name).map(|(button, _)| button).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No key or mouse button found associated with '{}'",
+                name.to_lowercase()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompileContext, Layer};
+    use crate::{compiler::button::Button, keymap::KeyMap, mousemap::MouseMap};
+    use inputbot::{KeybdKey, MouseButton};
+
+    #[test]
+    fn resolves_names() {
+        let context = CompileContext::default();
+        assert_eq!(
+            context.button("Space").unwrap(),
+            Button::K(KeybdKey::SpaceKey)
+        );
+        assert_eq!(
+            context.button("lmb").unwrap(),
+            Button::M(MouseButton::LeftButton)
+        );
+        assert!(context.button("nothing").is_err());
+    }
+
+    #[test]
+    fn resolves_layout_names() {
+        assert!(CompileContext::default().button("comma").is_err());
+        for name in ["ch-fr", "us", "de", "fr"] {
+            let layout = inputbot::KeyboardLayout::by_name(name).unwrap();
+            let context = CompileContext::default().with_keyboard_layout(layout);
+            assert_eq!(
+                context.button("comma").unwrap(),
+                Button::K(KeybdKey::CommaKey)
+            );
+            assert_eq!(
+                context.button("esc").unwrap(),
+                Button::K(KeybdKey::EscapeKey)
+            );
+        }
+
+        let swiss_french = CompileContext::default().with_keyboard_layout(inputbot::SWISS_FRENCH);
+        assert_eq!(
+            swiss_french.button("eaccgrave").unwrap(),
+            Button::K(KeybdKey::OEM1)
+        );
+        let us = CompileContext::default()
+            .with_keyboard_layout(inputbot::KeyboardLayout::by_name("us").unwrap());
+        assert!(us.button("eaccgrave").is_err());
+    }
+
+    #[test]
+    fn independent_contexts() {
+        let custom = CompileContext::default().with_layer(
+            Layer::App,
+            None,
+            KeyMap::from(vec![("jump".to_string(), KeybdKey::SpaceKey)]),
+            MouseMap::from(vec![("fire".to_string(), MouseButton::LeftButton)]),
+        );
+        let default = CompileContext::default();
+        assert_eq!(
+            custom.button("jump").unwrap(),
+            Button::K(KeybdKey::SpaceKey)
+        );
+        assert_eq!(
+            custom.button("fire").unwrap(),
+            Button::M(MouseButton::LeftButton)
+        );
+        assert!(default.button("jump").is_err());
+    }
+
+    #[test]
+    fn layers_shadow_in_order() {
+        let context = CompileContext::default()
+            .with_layer(
+                Layer::User,
+                None,
+                KeyMap::from(vec![
+                    ("jump".to_string(), KeybdKey::SpaceKey),
+                    ("dash".to_string(), KeybdKey::LShiftKey),
+                ]),
+                MouseMap::from(Vec::new()),
+            )
+            .with_layer(
+                Layer::App,
+                None,
+                KeyMap::from(vec![("jump".to_string(), KeybdKey::WKey)]),
+                MouseMap::from(Vec::new()),
+            );
+
+        let layer_of = |name| context.resolve(name).map(|(_, found)| found.layer);
+        assert_eq!(context.button("jump").unwrap(), Button::K(KeybdKey::WKey));
+        assert_eq!(layer_of("jump"), Some(Layer::App));
+        assert_eq!(
+            context.button("dash").unwrap(),
+            Button::K(KeybdKey::LShiftKey)
+        );
+        assert_eq!(layer_of("dash"), Some(Layer::User));
+        assert_eq!(layer_of("space"), Some(Layer::BuiltIn));
+        assert_eq!(layer_of("nothing"), None);
+    }
+
+    #[test]
+    fn lists_visible_names() {
+        let context = CompileContext::default().with_layer(
+            Layer::App,
+            None,
+            KeyMap::from(vec![
+                ("space".to_string(), KeybdKey::WKey),
+                ("lmb".to_string(), KeybdKey::AKey),
+                ("Jump".to_string(), KeybdKey::SpaceKey),
+            ]),
+            MouseMap::from(vec![("lmb".to_string(), MouseButton::RightButton)]),
+        );
+        let names = context.names();
+        let found = |name| {
+            names
+                .iter()
+                .filter(|(other, ..)| *other == name)
+                .map(|(_, button, layer)| (*button, layer.layer))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            found("space"),
+            vec![(Button::K(KeybdKey::WKey), Layer::App)]
+        );
+        assert_eq!(found("lmb"), vec![(Button::K(KeybdKey::AKey), Layer::App)]);
+        assert_eq!(found("Jump"), vec![]);
+        assert_eq!(
+            found("esc"),
+            vec![(Button::K(KeybdKey::EscapeKey), Layer::BuiltIn)]
+        );
+        assert!(names.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+}
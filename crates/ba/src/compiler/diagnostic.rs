@@ -0,0 +1,174 @@
+use super::{SourceMap, Span};
+
+/// Stable identifiers of every diagnostic the compiler can emit,
+/// existing codes must never be reassigned
+pub mod codes {
+    pub const UNKNOWN_TOKEN: &str = "E0001";
+    pub const UNEXPECTED_TOKEN: &str = "E0002";
+    pub const UNEXPECTED_EOF: &str = "E0003";
+    pub const UNKNOWN_DEFINITION: &str = "E0004";
+    pub const UNKNOWN_BUTTON: &str = "E0005";
+    pub const INVALID_NUMBER: &str = "E0006";
+    pub const UNCLOSED_BLOCK: &str = "E0007";
+    pub const INVALID_IN_BIND: &str = "E0008";
+    pub const INVALID_IN_REPEAT: &str = "E0009";
+    pub const UNDEFINED_VARIABLE: &str = "E0010";
+    pub const INVALID_OPERATION: &str = "E0011";
+    pub const INVALID_IN_CONDITION: &str = "E0012";
+    pub const INVALID_REGEX: &str = "E0013";
+    pub const INVALID_CONDITION: &str = "E0014";
+    pub const UNDEFINED_FUNCTION: &str = "E0015";
+    pub const ARGUMENT_COUNT: &str = "E0016";
+    pub const DUPLICATE_DEFINITION: &str = "E0017";
+    pub const INVALID_IN_FUNCTION: &str = "E0018";
+    pub const INCLUDE_FAILED: &str = "E0019";
+    pub const INCLUDE_CYCLE: &str = "E0020";
+    pub const CONFLICTING_DEFINITION: &str = "E0021";
+    pub const DUPLICATE_BUTTON: &str = "E0022";
+    pub const INVALID_HOTKEY: &str = "E0023";
+    pub const UNKNOWN_LAYOUT: &str = "E0024";
+    pub const INCLUDE_OUTSIDE: &str = "E0025";
+    pub const EMPTY_REPEAT: &str = "E0026";
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub span: Span,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+            span,
+            help: None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn warning(code: &'static str, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(code, message, span)
+        }
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Renders the diagnostic with the offending source line and a caret underline, e.g.
+    /// ```text
+    /// error[E0005]: No key or mouse button found associated with 'lbm'
+    ///  --> main.ba:3:7
+    ///   |
+    /// 3 | Press lbm
+    ///   |       ^^^
+    ///   = help: names are defined in keymap.json and mousemap.json
+    /// ```
+    pub fn render(&self, sources: &SourceMap) -> String {
+        let source = sources.get(self.span.file);
+        let input = source.content.as_str();
+        let start = self.span.start.min(input.len());
+        let line_start = input[..start].rfind('\n').map(|idx| idx + 1).unwrap_or(0);
+        let line_end = input[start..]
+            .find('\n')
+            .map(|idx| start + idx)
+            .unwrap_or(input.len());
+        let (line_number, column) = source.position(start);
+        let source_line = &input[line_start..line_end];
+        // only the first line of a multi-line span is underlined
+        let underline_len = input[start..self.span.end.clamp(start, line_end)]
+            .chars()
+            .count()
+            .max(1);
+
+        let gutter = " ".repeat(line_number.to_string().len());
+        let mut rendered = format!(
+            "{}[{}]: {}\n{gutter}--> {}:{}:{}\n{gutter} |\n{} | {}\n{gutter} | {}{}",
+            self.severity,
+            self.code,
+            self.message,
+            source.name,
+            line_number,
+            column,
+            line_number,
+            source_line,
+            " ".repeat(column - 1),
+            "^".repeat(underline_len),
+        );
+        if let Some(help) = &self.help {
+            rendered.push_str(&format!("\n{gutter} = help: {}", help));
+        }
+        rendered
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_caret() {
+        let mut sources = SourceMap::default();
+        sources.add(
+            "main.ba",
+            "define RESOLUTION = 1920, 1080\nPress lbm\nSleep 1",
+        );
+        let diagnostic = Diagnostic::error(
+            codes::UNKNOWN_BUTTON,
+            "No key or mouse button found associated with 'lbm'",
+            Span::from(37..40),
+        )
+        .with_help("names are defined in keymap.json and mousemap.json");
+        assert_eq!(
+            diagnostic.render(&sources),
+            "error[E0005]: No key or mouse button found associated with 'lbm'\n --> main.ba:2:7\n  |\n2 | Press lbm\n  |       ^^^\n  = help: names are defined in keymap.json and mousemap.json"
+        );
+    }
+
+    #[test]
+    fn render_end_of_line() {
+        let mut sources = SourceMap::default();
+        sources.add("main.ba", "Sleep\n");
+        let diagnostic = Diagnostic::error(
+            codes::UNEXPECTED_TOKEN,
+            "Expected 'Float', found 'EOI'",
+            Span::from(5..6),
+        );
+        assert_eq!(
+            diagnostic.render(&sources),
+            "error[E0002]: Expected 'Float', found 'EOI'\n --> main.ba:1:6\n  |\n1 | Sleep\n  |      ^"
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use crate::compiler::button::Hotkey;
+
+/// Everything the main thread of the engine reacts to, sent by the watcher, the executor
+/// and the binds over a single channel
+#[derive(Debug)]
+pub(super) enum EngineEvent {
+    /// the global halt key was pressed
+    Halt,
+    /// the global pause key was pressed, the execution is now paused if true
+    PauseToggled { paused: bool, key: Hotkey },
+    /// a bind failed, the run goes on
+    BindFailed {
+        hotkey: Hotkey,
+        error: anyhow::Error,
+    },
+    /// the executor went through every cycle, failed or was cancelled
+    ExecutorDone(anyhow::Result<()>),
+}
@@ -1,39 +1,65 @@
-use std::time::Duration;
+use std::{
+    sync::{
+        Arc, Mutex, MutexGuard, Once,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    time::{Duration, Instant},
+};
 
-use crate::compiler::expression::adapt_expressions;
+use crate::compiler::expression::execute_all;
 
-use super::{button::Button, expression::Expression};
+use super::{
+    button::{Button, Hotkey},
+    expression::Expression,
+};
 
+mod cancel;
+mod event;
+mod runtime;
 mod watcher;
 use anyhow::Context;
-use oneshot::TryRecvError;
+pub(super) use cancel::{Cancelled, is_cancelled};
+use event::EngineEvent;
+use inputbot::{Clock, HotkeyHandle, InputBackend, KeyboardLayout, SWISS_FRENCH};
+pub(super) use runtime::Runtime;
 use watcher::Watcher;
 
 pub struct Engine {
     inner: Vec<Expression>,
     watcher: Watcher,
-    delay: std::time::Duration,
-    buttons_in_use: Vec<Button>,
+    runtime: Runtime,
+    // binds stay registered as long as the engine lives
+    binds: Vec<HotkeyHandle>,
+    events: (Sender<EngineEvent>, Receiver<EngineEvent>),
 }
 
 impl Engine {
-    // 50 milliseconds
-    const MAIN_THREAD_DELAY_BETWEEN_CHECKS: Duration = Duration::new(0, 50000000);
+    // blocking expressions notice the cancellation within their polling interval
+    const EXECUTOR_STOP_TIMEOUT: Duration = Duration::from_secs(1);
 
     pub fn new(
         mut expressions: Vec<Expression>,
         host_resolution: (i32, i32),
+        backend: Arc<dyn InputBackend>,
+        clock: Arc<dyn Clock>,
     ) -> anyhow::Result<Self> {
+        let problems = Self::validate(&expressions);
+        if !problems.is_empty() {
+            for problem in problems.iter() {
+                tracing::error!("{}", problem);
+            }
+            Err(anyhow::anyhow!("Failed to create engine"))?
+        }
+
+        // presence checked by validate
         let script_resolution = expressions
             .iter()
             .find_map(|expr| match expr {
                 Expression::Resolution(res) => Some(*res),
                 _ => None,
             })
-            .ok_or_else(|| {
-                tracing::error!("GLOBAL_RESOLUTION definition missing");
-                anyhow::anyhow!("Failed to create engine")
-            })?;
+            .context("RESOLUTION definition missing")?;
         tracing::debug!(
             "script resolution = {}x{}",
             script_resolution.0,
@@ -48,10 +74,7 @@ impl Engine {
                 }
                 _ => None,
             })
-            .ok_or_else(|| {
-                tracing::error!("DELAY_BETWEEN_ACTIONS definition missing");
-                anyhow::anyhow!("Failed to create engine")
-            })?;
+            .context("DELAY_BETWEEN_ACTIONS definition missing")?;
         tracing::debug!(
             "delay between actions = {} ms",
             delay_between_actions.as_millis()
@@ -60,110 +83,947 @@ impl Engine {
         let global_halt_key = expressions
             .iter()
             .find_map(|expr| match expr {
-                Expression::GlobalHaltKey(button) => Some(*button),
+                Expression::GlobalHaltKey(hotkey) => Some(*hotkey),
                 _ => None,
             })
-            .ok_or_else(|| {
-                tracing::error!("GLOBAL_HALT_KEY definition missing");
-                anyhow::anyhow!("Failed to create engine")
-            })?;
-        tracing::debug!("global halt key = {:?}", global_halt_key);
+            .context("GLOBAL_HALT_KEY definition missing")?;
+        tracing::debug!("global halt key = {}", global_halt_key);
+
+        Self::select_layout(&expressions, backend.as_ref())?;
+
+        let runtime = Runtime::new(
+            backend.clone(),
+            clock,
+            delay_between_actions,
+            host_resolution,
+            script_resolution,
+        );
+
+        let (sender, receiver) = mpsc::channel();
 
         // extract and launch binds
-        let mut buttons_in_use = vec![global_halt_key];
+        let mut binds = Vec::new();
+        if let Some(pause_key) = expressions.iter().find_map(|expr| match expr {
+            Expression::GlobalPauseKey(hotkey) => Some(*hotkey),
+            _ => None,
+        }) {
+            tracing::debug!("global pause key = {}", pause_key);
+            let pause_runtime = runtime.clone();
+            let events = sender.clone();
+            binds.push(pause_key.detached_hotkey(backend.as_ref(), move || {
+                let _ = events.send(EngineEvent::PauseToggled {
+                    paused: pause_runtime.toggle_pause(),
+                    key: pause_key,
+                });
+            })?);
+        }
         while let Some(idx) = expressions
             .iter()
             .position(|expr| matches!(expr, Expression::Bind(..)))
         {
-            let (button, sub_expressions) = match expressions.remove(idx) {
-                Expression::Bind(key, sub_expressions) => (key, sub_expressions),
+            let (hotkey, sub_expressions) = match expressions.remove(idx) {
+                Expression::Bind(hotkey, sub_expressions) => (hotkey, sub_expressions),
                 _ => unreachable!(),
             };
-            let sub_expressions =
-                adapt_expressions(sub_expressions, host_resolution, script_resolution);
-
-            tracing::info!("Attempting to bind '{:?}' as a HotKey", button);
+            tracing::info!("Attempting to bind '{}' as a HotKey", hotkey);
             tracing::trace!("with subexpressions {:?}", sub_expressions);
-            if buttons_in_use.contains(&button) {
-                Err(anyhow::anyhow!(
-                    "Failed to bind the button '{:?}' as it is already in use",
-                    &button
-                ))?
-            }
-            buttons_in_use.push(button);
-            button.detached_hotkey(move || {
-                for expr in sub_expressions.iter() {
-                    expr.execute();
-                    std::thread::sleep(delay_between_actions);
+            let bind_runtime = runtime.clone();
+            let events = sender.clone();
+            binds.push(hotkey.detached_hotkey(backend.as_ref(), move || {
+                match execute_all(&sub_expressions, &bind_runtime) {
+                    Err(err) if is_cancelled(&err) => {
+                        tracing::debug!("Bind on '{}' cancelled", hotkey)
+                    }
+                    Err(error) => {
+                        let _ = events.send(EngineEvent::BindFailed { hotkey, error });
+                    }
+                    Ok(()) => (),
                 }
-            })?;
+            })?);
         }
 
-        let expressions = adapt_expressions(expressions, host_resolution, script_resolution);
+        expressions.retain(|expr| !expr.is_handled_at_init());
 
         Ok(Self {
             inner: expressions,
-            watcher: Watcher::new(global_halt_key)?,
-            delay: delay_between_actions,
-            buttons_in_use,
+            watcher: Watcher::new(global_halt_key, backend.as_ref(), sender.clone())?,
+            runtime,
+            binds,
+            events: (sender, receiver),
         })
     }
 
+    /// Checks that the active keyboard layout is the one the script was written for,
+    /// without a KEYBOARD_LAYOUT definition it was written for Swiss French
+    fn select_layout(expressions: &[Expression], backend: &dyn InputBackend) -> anyhow::Result<()> {
+        let layout = expressions
+            .iter()
+            .find_map(|expr| match expr {
+                Expression::KeyboardLayout(layout) => Some(*layout),
+                _ => None,
+            })
+            .unwrap_or_else(|| {
+                tracing::debug!("No KEYBOARD_LAYOUT defined, defaulting to {}", SWISS_FRENCH);
+                SWISS_FRENCH
+            });
+        let active_id = backend.keyboard_layout()?;
+        if let Some(id) = active_id.filter(|id| KeyboardLayout::by_id(id) != Some(layout)) {
+            tracing::error!(
+                "Expected '{}' or '{}' for keyboard layout, got '{}'",
+                layout.klid,
+                layout.xkb,
+                id
+            );
+            Err(anyhow::anyhow!(
+                "Invalid keyboard layout, switch to {}",
+                layout.description
+            ))?
+        }
+        tracing::info!("Using the {} keyboard layout", layout);
+        backend.set_keyboard_layout(layout);
+        Ok(())
+    }
+
+    /// Returns every problem that would prevent the engine from being created,
+    /// nothing is registered nor executed
+    pub fn validate(expressions: &[Expression]) -> Vec<anyhow::Error> {
+        let mut problems = Vec::new();
+
+        // a hotkey only triggers for its exact modifiers, `ctrl+a` and `a` do not conflict
+        let mut hotkeys_in_use: Vec<Hotkey> = Vec::new();
+        let in_use = |used: &[Hotkey], hotkey: &Hotkey| used.iter().any(|u| u.overlaps(hotkey));
+        let global_halt_key = expressions.iter().find_map(|expr| match expr {
+            Expression::GlobalHaltKey(hotkey) => Some(*hotkey),
+            _ => None,
+        });
+        if !expressions
+            .iter()
+            .any(|expr| matches!(expr, Expression::Resolution(_)))
+        {
+            problems.push(anyhow::anyhow!("RESOLUTION definition missing"));
+        }
+        if !expressions
+            .iter()
+            .any(|expr| matches!(expr, Expression::DelayBetweenActions(_)))
+        {
+            problems.push(anyhow::anyhow!("DELAY_BETWEEN_ACTIONS definition missing"));
+        }
+        match global_halt_key {
+            None => problems.push(anyhow::anyhow!("GLOBAL_HALT_KEY definition missing")),
+            Some(hotkey) if matches!(hotkey.button, Button::M(_)) => {
+                problems.push(anyhow::anyhow!(
+                    "Cannot use the mouse button '{}' as the global halt key, use a key instead",
+                    hotkey
+                ))
+            }
+            Some(hotkey) => hotkeys_in_use.push(hotkey),
+        }
+        for expr in expressions.iter() {
+            if let Expression::GlobalPauseKey(hotkey) = expr {
+                if in_use(&hotkeys_in_use, hotkey) {
+                    problems.push(anyhow::anyhow!(
+                        "Cannot use '{}' as the global pause key as it is already in use",
+                        hotkey
+                    ))
+                } else {
+                    hotkeys_in_use.push(*hotkey)
+                }
+            }
+        }
+
+        for expr in expressions.iter() {
+            match expr {
+                Expression::Bind(hotkey, _) if in_use(&hotkeys_in_use, hotkey) => problems.push(
+                    anyhow::anyhow!("Failed to bind '{}' as it is already in use", hotkey),
+                ),
+                Expression::Bind(hotkey, _) => hotkeys_in_use.push(*hotkey),
+                _ => (),
+            }
+        }
+
+        for expr in flatten(expressions) {
+            match expr {
+                Expression::AwaitKey(hotkey) if in_use(&hotkeys_in_use, hotkey) => problems.push(
+                    anyhow::anyhow!("Cannot use '{}' to await as it is already in use", hotkey),
+                ),
+                _ => (),
+            }
+        }
+
+        problems
+    }
+
     pub fn start(self, nb_cycles: usize) -> anyhow::Result<()> {
-        let executor_receiver =
-            Self::spawn_executor(self.inner, self.delay, nb_cycles, self.buttons_in_use)
-                .context("Failed to spawn executor thread")?;
+        let Self {
+            inner,
+            watcher,
+            runtime,
+            binds,
+            events,
+        } = self;
+        // nothing stays pressed whichever way the run ends
+        let _release = ReleaseGuard::new(&runtime);
+        let result = Self::watch(inner, nb_cycles, watcher, &runtime, events);
+        // bind callbacks still running stop instead of pressing inputs after the release
+        runtime.halt();
+        tracing::debug!("Unregistering {} bind(s)", binds.len());
+        drop(binds);
+        result
+    }
+
+    /// Blocks on the engine events until the run is over
+    fn watch(
+        expressions: Vec<Expression>,
+        nb_cycles: usize,
+        watcher: Watcher,
+        runtime: &Runtime,
+        (sender, events): (Sender<EngineEvent>, Receiver<EngineEvent>),
+    ) -> anyhow::Result<()> {
+        Self::spawn_executor(expressions, nb_cycles, runtime.clone(), sender)
+            .context("Failed to spawn executor thread")?;
         loop {
-            if self.watcher.check() {
-                self.watcher.post_halt();
-                return Ok(());
+            // the watcher and the binds hold a sender as long as they live
+            let event = events
+                .recv()
+                .context("Every source of engine events disconnected")?;
+            match event {
+                EngineEvent::Halt => {
+                    tracing::info!("Halt message received");
+                    runtime.halt();
+                    watcher.post_halt();
+                    Self::wait_for_executor(&events);
+                    return Ok(());
+                }
+                EngineEvent::PauseToggled { paused: true, key } => {
+                    tracing::info!("Execution paused, press '{}' to resume", key)
+                }
+                EngineEvent::PauseToggled { paused: false, .. } => {
+                    tracing::info!("Execution resumed")
+                }
+                EngineEvent::BindFailed { hotkey, error } => {
+                    tracing::error!("Bind on '{}' failed, {:#}", hotkey, error)
+                }
+                EngineEvent::ExecutorDone(result) => {
+                    return result.context("Script execution failed");
+                }
             }
-            match executor_receiver.try_recv() {
-                Ok(()) => return Ok(()),
-                Err(TryRecvError::Empty) => (),
-                Err(TryRecvError::Disconnected) => {
-                    tracing::error!("Executor thread disconnected");
-                    Err(anyhow::anyhow!(
-                        "Program halted, executor thread stopped unexpectedly"
-                    ))?
+        }
+    }
+
+    /// Gives the cancelled executor some time to stop, the remaining events are dropped
+    fn wait_for_executor(events: &Receiver<EngineEvent>) {
+        let deadline = Instant::now() + Self::EXECUTOR_STOP_TIMEOUT;
+        loop {
+            match events.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(EngineEvent::ExecutorDone(_)) => {
+                    return tracing::debug!("Executor stopped after the halt");
+                }
+                Ok(event) => tracing::trace!("Dropped '{:?}' after the halt", event),
+                Err(_) => {
+                    return tracing::warn!(
+                        "Executor still running {} ms after the halt",
+                        Self::EXECUTOR_STOP_TIMEOUT.as_millis()
+                    );
                 }
             }
-            std::thread::sleep(Self::MAIN_THREAD_DELAY_BETWEEN_CHECKS);
         }
     }
 
-    pub fn spawn_executor(
+    fn spawn_executor(
         expressions: Vec<Expression>,
-        delay: std::time::Duration,
         nb_cycles: usize,
-        buttons_in_use: Vec<Button>,
-    ) -> anyhow::Result<oneshot::Receiver<()>> {
-        let (sender, receiver) = oneshot::channel::<()>();
-
+        runtime: Runtime,
+        events: Sender<EngineEvent>,
+    ) -> anyhow::Result<()> {
         std::thread::Builder::new()
             .name(String::from("Executor"))
             .spawn(move || {
-                'outer: for cycle_idx in 0..nb_cycles {
-                    tracing::info!("cycle {}/{}", cycle_idx + 1, nb_cycles);
-                    for expr in expressions.iter() {
-                        if let Expression::AwaitKey(button) = expr {
-                            if buttons_in_use.contains(button) {
-                                tracing::error!(
-                                    "Cannot use '{:?}' to await as it is already in use",
-                                    button
-                                );
-                                break 'outer;
-                            }
-                        }
-                        expr.execute();
-                        std::thread::sleep(delay)
-                    }
+                let run = || {
+                    (0..nb_cycles).try_for_each(|cycle_idx| {
+                        tracing::info!("cycle {}/{}", cycle_idx + 1, nb_cycles);
+                        execute_all(&expressions, &runtime)
+                    })
+                };
+                // a panic must still reach the main thread, which would otherwise wait forever
+                let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(run))
+                    .unwrap_or_else(|_| {
+                        tracing::error!("Executor thread panicked");
+                        Err(anyhow::anyhow!(
+                            "Program halted, executor thread stopped unexpectedly"
+                        ))
+                    });
+                if let Err(err) = &result
+                    && is_cancelled(err)
+                {
+                    tracing::info!("Executor cancelled");
                 }
-                if let Err(err) = sender.send(()) {
+                if let Err(err) = events.send(EngineEvent::ExecutorDone(result)) {
                     tracing::error!("Executor failed to signal the main thread: '{err}'")
                 };
             })?;
+        Ok(())
+    }
+}
+
+/// Releases every input still held by the script once dropped, or as soon as any thread
+/// panics since release builds abort instead of unwinding
+struct ReleaseGuard<'a> {
+    runtime: &'a Runtime,
+    id: usize,
+}
+
+// runtimes of the engines currently running, released by the panic hook
+static RUNNING: Mutex<Vec<(usize, Runtime)>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+static PANIC_HOOK: Once = Once::new();
+
+impl<'a> ReleaseGuard<'a> {
+    fn new(runtime: &'a Runtime) -> Self {
+        // installed once and kept for the whole process, the hook it wraps is still called
+        PANIC_HOOK.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                for (_, runtime) in running().iter() {
+                    runtime.release_all();
+                }
+                previous(info)
+            }));
+        });
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        running().push((id, runtime.clone()));
+        Self { runtime, id }
+    }
+}
+
+impl Drop for ReleaseGuard<'_> {
+    fn drop(&mut self) {
+        running().retain(|(id, _)| *id != self.id);
+        let released = self.runtime.release_all();
+        if !released.is_empty() {
+            tracing::info!(
+                "Released {} input(s) held at the end of the run",
+                released.len()
+            );
+        }
+    }
+}
 
-        Ok(receiver)
+fn running() -> MutexGuard<'static, Vec<(usize, Runtime)>> {
+    RUNNING.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+// Every expression, including the ones nested in repeat and if blocks
+fn flatten(expressions: &[Expression]) -> Vec<&Expression> {
+    expressions
+        .iter()
+        .flat_map(|expr| {
+            let mut nested = vec![expr];
+            match expr {
+                Expression::Repeat(_, inner) => nested.extend(flatten(inner)),
+                Expression::Call(function, _) => nested.extend(flatten(&function.body)),
+                Expression::If(_, then, otherwise) => {
+                    nested.extend(flatten(then));
+                    nested.extend(flatten(otherwise));
+                }
+                _ => (),
+            }
+            nested
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Engine;
+    use crate::compiler::{
+        button::{Button, Chord, Hotkey},
+        condition::{Condition, Pattern},
+        expression::{Expression, Function},
+        value::{Operator, Term},
+    };
+    use inputbot::{
+        Clock, InputEvent, KeybdKey::*, Modifiers, MouseButton::*, RealClock, RecordedEvent,
+        RecordingBackend, SimulatedClock,
+    };
+    use std::{sync::Arc, time::Duration};
+
+    fn header() -> Vec<Expression> {
+        vec![
+            Expression::Resolution((1920, 1080)),
+            Expression::DelayBetweenActions(0),
+            Expression::GlobalHaltKey(Button::K(EscapeKey).into()),
+        ]
+    }
+
+    /// Engine running on a 1920x1080 monitor with the clock of the backend
+    fn engine(
+        expressions: Vec<Expression>,
+        backend: Arc<RecordingBackend>,
+    ) -> anyhow::Result<Engine> {
+        let clock = backend.clock();
+        Engine::new(expressions, (1920, 1080), backend, clock)
+    }
+
+    #[test]
+    fn tap_emits_one_down_up_pair() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Tap(Button::K(LControlKey).into()));
+        expressions.push(Expression::Move(Term::int(960), Term::int(540)));
+        Engine::new(
+            expressions,
+            (3840, 2160),
+            backend.clone(),
+            Arc::new(RealClock::new()),
+        )
+        .unwrap()
+        .start(2)
+        .unwrap();
+        assert_eq!(
+            backend.inputs(),
+            vec![
+                InputEvent::KeyDown(LControlKey),
+                InputEvent::KeyUp(LControlKey),
+                InputEvent::MoveAbs(1920, 1080),
+                InputEvent::KeyDown(LControlKey),
+                InputEvent::KeyUp(LControlKey),
+                InputEvent::MoveAbs(1920, 1080),
+            ]
+        )
+    }
+
+    #[test]
+    fn chord_releases_in_reverse() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Tap(Chord(vec![
+            Button::K(LControlKey),
+            Button::M(LeftButton),
+        ])));
+        engine(expressions, backend.clone())
+            .unwrap()
+            .start(1)
+            .unwrap();
+        assert_eq!(
+            backend.inputs(),
+            vec![
+                InputEvent::KeyDown(LControlKey),
+                InputEvent::MouseDown(LeftButton),
+                InputEvent::MouseUp(LeftButton),
+                InputEvent::KeyUp(LControlKey),
+            ]
+        )
+    }
+
+    #[test]
+    fn halt_key_stops_engine() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Press(Button::M(LeftButton).into()));
+        expressions.push(Expression::Await);
+        let engine = engine(expressions, backend.clone()).unwrap();
+        let handle = std::thread::spawn(move || engine.start(1));
+        assert!(backend.wait_until(|events| !events.is_empty(), Duration::from_secs(1)));
+        assert_eq!(backend.trigger_hotkey(EscapeKey), 1);
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn bind_callback_fires() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Bind(
+            Button::K(Numrow1Key).into(),
+            vec![Expression::Type(Term::string("hello"))],
+        ));
+        expressions.push(Expression::Await);
+        let engine = engine(expressions, backend.clone()).unwrap();
+        let handle = std::thread::spawn(move || engine.start(1));
+        assert_eq!(backend.trigger_hotkey(Numrow1Key), 1);
+        assert!(backend.wait_until(|events| events.len() == 1, Duration::from_secs(1)));
+        assert_eq!(
+            backend.inputs(),
+            vec![InputEvent::Text("hello".to_string())]
+        );
+        backend.trigger_hotkey(EscapeKey);
+        handle.join().unwrap().unwrap();
+        assert!(!backend.is_listening(Numrow1Key));
+        assert!(!backend.is_listening(EscapeKey));
+    }
+
+    #[test]
+    fn mouse_bind_fires() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Bind(
+            Button::M(X2Button).into(),
+            vec![Expression::Tap(Button::K(AKey).into())],
+        ));
+        expressions.push(Expression::AwaitKey(Button::M(MiddleButton).into()));
+        let engine = engine(expressions, backend.clone()).unwrap();
+        let handle = std::thread::spawn(move || engine.start(1));
+        assert_eq!(backend.trigger_hotkey(X2Button), 1);
+        assert!(backend.wait_until(|events| events.len() == 2, Duration::from_secs(1)));
+        // the executor awaits the middle button once it is registered
+        let start = std::time::Instant::now();
+        while backend.trigger_hotkey(MiddleButton) == 0 {
+            assert!(start.elapsed() < Duration::from_secs(1));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        handle.join().unwrap().unwrap();
+        assert!(!backend.is_listening(X2Button));
+    }
+
+    #[test]
+    fn pause_key_suspends_execution() {
+        let clock = Arc::new(SimulatedClock::new());
+        let backend = Arc::new(RecordingBackend::new().with_clock(clock.clone()));
+        let mut expressions = header();
+        expressions.push(Expression::GlobalPauseKey(Button::K(F8Key).into()));
+        expressions.push(Expression::Sleep(Term::float(10.0)));
+        expressions.push(Expression::Tap(Button::K(AKey).into()));
+        let engine = engine(expressions, backend.clone()).unwrap();
+        // paused before the first expression
+        assert_eq!(backend.trigger_hotkey(F8Key), 1);
+        let handle = std::thread::spawn(move || engine.start(1));
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(backend.events().is_empty());
+        assert_eq!(clock.now(), Duration::ZERO);
+        assert_eq!(backend.trigger_hotkey(F8Key), 1);
+        handle.join().unwrap().unwrap();
+        assert_eq!(
+            backend.events()[0],
+            RecordedEvent {
+                at: Duration::from_secs(10),
+                event: InputEvent::KeyDown(AKey)
+            }
+        );
+        assert!(!backend.is_listening(F8Key));
+    }
+
+    #[test]
+    fn held_inputs_released_on_halt() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Press(Chord(vec![
+            Button::K(LControlKey),
+            Button::M(LeftButton),
+        ])));
+        expressions.push(Expression::Press(Button::K(AKey).into()));
+        expressions.push(Expression::Release(Button::K(AKey).into()));
+        expressions.push(Expression::Await);
+        let engine = engine(expressions, backend.clone()).unwrap();
+        let handle = std::thread::spawn(move || engine.start(1));
+        assert!(backend.wait_until(|events| events.len() == 4, Duration::from_secs(1)));
+        backend.trigger_hotkey(EscapeKey);
+        handle.join().unwrap().unwrap();
+        assert_eq!(
+            backend.inputs()[4..],
+            [
+                InputEvent::MouseUp(LeftButton),
+                InputEvent::KeyUp(LControlKey)
+            ]
+        );
+    }
+
+    #[test]
+    fn held_inputs_released_on_error() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Press(Button::K(LShiftKey).into()));
+        expressions.push(Expression::Sleep(Term::string("soon")));
+        let result = engine(expressions, backend.clone()).unwrap().start(1);
+        assert!(result.is_err());
+        assert_eq!(
+            backend.inputs(),
+            vec![InputEvent::KeyDown(LShiftKey), InputEvent::KeyUp(LShiftKey)]
+        );
+    }
+
+    #[test]
+    fn running_bind_stops_with_executor() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Bind(
+            Button::K(Numrow1Key).into(),
+            vec![
+                Expression::Press(Button::K(AKey).into()),
+                Expression::Sleep(Term::float(0.2)),
+                Expression::Tap(Button::K(BKey).into()),
+            ],
+        ));
+        expressions.push(Expression::Sleep(Term::float(0.1)));
+        let engine = engine(expressions, backend.clone()).unwrap();
+        let handle = std::thread::spawn(move || engine.start(1));
+        assert_eq!(backend.trigger_hotkey(Numrow1Key), 1);
+        assert!(backend.wait_until(|events| events.len() == 1, Duration::from_secs(1)));
+        handle.join().unwrap().unwrap();
+        // the bind was halted, nothing is pressed after the release
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(
+            backend.inputs(),
+            vec![InputEvent::KeyDown(AKey), InputEvent::KeyUp(AKey)]
+        );
+    }
+
+    #[test]
+    fn failed_bind_keeps_running() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Bind(
+            Button::K(Numrow1Key).into(),
+            vec![Expression::Sleep(Term::string("soon"))],
+        ));
+        expressions.push(Expression::Bind(
+            Button::K(Numrow2Key).into(),
+            vec![Expression::Tap(Button::K(AKey).into())],
+        ));
+        expressions.push(Expression::Await);
+        let engine = engine(expressions, backend.clone()).unwrap();
+        let handle = std::thread::spawn(move || engine.start(1));
+        assert_eq!(backend.trigger_hotkey(Numrow1Key), 1);
+        assert_eq!(backend.trigger_hotkey(Numrow2Key), 1);
+        assert!(backend.wait_until(|events| events.len() == 2, Duration::from_secs(1)));
+        assert!(!handle.is_finished());
+        backend.trigger_hotkey(EscapeKey);
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn simulated_timeline() {
+        let clock = Arc::new(SimulatedClock::new());
+        let backend = Arc::new(RecordingBackend::new().with_clock(clock.clone()));
+        let expressions = vec![
+            Expression::Resolution((1920, 1080)),
+            Expression::DelayBetweenActions(50),
+            Expression::GlobalHaltKey(Button::K(EscapeKey).into()),
+            Expression::Tap(Button::K(AKey).into()),
+            Expression::Sleep(Term::float(600.0)),
+            Expression::Tap(Button::M(LeftButton).into()),
+        ];
+        engine(expressions, backend.clone())
+            .unwrap()
+            .start(1)
+            .unwrap();
+        let at = |millis| Duration::from_millis(millis);
+        assert_eq!(
+            backend.events(),
+            vec![
+                RecordedEvent {
+                    at: at(0),
+                    event: InputEvent::KeyDown(AKey)
+                },
+                RecordedEvent {
+                    at: at(0),
+                    event: InputEvent::KeyUp(AKey)
+                },
+                RecordedEvent {
+                    at: at(600_100),
+                    event: InputEvent::MouseDown(LeftButton)
+                },
+                RecordedEvent {
+                    at: at(600_100),
+                    event: InputEvent::MouseUp(LeftButton)
+                },
+            ]
+        );
+        assert_eq!(clock.now(), at(600_150));
+    }
+
+    fn ctrl(key: inputbot::KeybdKey) -> Hotkey {
+        Hotkey {
+            modifiers: Modifiers {
+                ctrl: true,
+                ..Default::default()
+            },
+            button: Button::K(key),
+            passthrough: false,
+        }
+    }
+
+    #[test]
+    fn keyboard_layout_selection() {
+        let swiss_french = inputbot::SWISS_FRENCH;
+        let german = inputbot::KeyboardLayout::by_name("de").unwrap();
+        let select = |layout: Option<&'static inputbot::KeyboardLayout>, active: Option<&str>| {
+            let mut backend = RecordingBackend::new();
+            if let Some(id) = active {
+                backend = backend.with_keyboard_layout(id);
+            }
+            let backend = Arc::new(backend);
+            let mut expressions = header();
+            expressions.extend(layout.map(Expression::KeyboardLayout));
+            let result = engine(expressions, backend.clone());
+            (result.is_ok(), backend.selected_layout())
+        };
+
+        // selected by the script
+        assert_eq!(select(Some(german), Some("00000407")), (true, Some(german)));
+        assert_eq!(select(Some(german), Some("de")), (true, Some(german)));
+        assert_eq!(select(Some(german), None), (true, Some(german)));
+        assert_eq!(select(Some(german), Some("0000100C")), (false, None));
+        assert_eq!(select(Some(german), Some("00000813")), (false, None));
+        // written for Swiss French without a definition
+        assert_eq!(select(None, Some("ch(fr)")), (true, Some(swiss_french)));
+        assert_eq!(select(None, Some("00000407")), (false, None));
+        assert_eq!(select(None, Some("00000813")), (false, None));
+        assert_eq!(select(None, None), (true, Some(swiss_french)));
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let expressions = vec![
+            Expression::GlobalHaltKey(Button::K(EscapeKey).into()),
+            Expression::GlobalPauseKey(Button::K(EscapeKey).into()),
+            Expression::Bind(Button::K(EscapeKey).into(), vec![]),
+            Expression::Bind(Button::M(X1Button).into(), vec![]),
+            Expression::Bind(
+                Hotkey {
+                    passthrough: true,
+                    ..Button::M(X1Button).into()
+                },
+                vec![],
+            ),
+            Expression::Bind(Button::K(Numrow1Key).into(), vec![]),
+            Expression::Bind(ctrl(Numrow1Key), vec![]),
+            Expression::AwaitKey(Button::K(Numrow1Key).into()),
+            Expression::AwaitKey(ctrl(Numrow1Key)),
+            Expression::AwaitKey(ctrl(Numrow2Key)),
+            Expression::AwaitKey(Button::M(MiddleButton).into()),
+            Expression::AwaitKey(Button::M(X1Button).into()),
+        ];
+        let problems: Vec<String> = Engine::validate(&expressions)
+            .iter()
+            .map(|problem| problem.to_string())
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                "RESOLUTION definition missing",
+                "DELAY_BETWEEN_ACTIONS definition missing",
+                "Cannot use 'EscapeKey' as the global pause key as it is already in use",
+                "Failed to bind 'EscapeKey' as it is already in use",
+                "Failed to bind 'X1Button' as it is already in use",
+                "Cannot use 'Numrow1Key' to await as it is already in use",
+                "Cannot use 'ctrl+Numrow1Key' to await as it is already in use",
+                "Cannot use 'X1Button' to await as it is already in use",
+            ]
+        );
+        assert!(Engine::validate(&header()).is_empty());
+    }
+
+    #[test]
+    fn repeat_runs_count_times() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Repeat(
+            Some(Term::int(20)),
+            vec![Expression::Tap(Button::K(DownKey).into())],
+        ));
+        Engine::new(
+            expressions,
+            (1920, 1080),
+            backend.clone(),
+            Arc::new(SimulatedClock::new()),
+        )
+        .unwrap()
+        .start(1)
+        .unwrap();
+        let inputs = backend.inputs();
+        assert_eq!(inputs.len(), 40);
+        assert!(
+            inputs
+                .chunks(2)
+                .all(|pair| pair == [InputEvent::KeyDown(DownKey), InputEvent::KeyUp(DownKey)])
+        );
+    }
+
+    #[test]
+    fn halt_key_stops_repeat_forever() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Repeat(
+            None,
+            vec![
+                Expression::Tap(Button::K(DownKey).into()),
+                Expression::Sleep(Term::float(0.01)),
+            ],
+        ));
+        let engine = engine(expressions, backend.clone()).unwrap();
+        let runtime = engine.runtime.clone();
+        let handle = std::thread::spawn(move || engine.start(1));
+        assert!(backend.wait_until(|events| events.len() >= 4, Duration::from_secs(1)));
+        assert_eq!(backend.trigger_hotkey(EscapeKey), 1);
+        handle.join().unwrap().unwrap();
+        assert!(runtime.is_halted());
+        // the current iteration finishes, no new one starts
+        std::thread::sleep(Duration::from_millis(50));
+        let count = backend.events().len();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(backend.events().len(), count);
+    }
+
+    #[test]
+    fn halt_cancels_blocking_expressions() {
+        for blocking in [
+            Expression::Sleep(Term::float(600.0)),
+            Expression::Await,
+            Expression::AwaitKey(Button::K(F2Key).into()),
+        ] {
+            let backend = Arc::new(RecordingBackend::new());
+            let mut expressions = header();
+            expressions.push(Expression::Tap(Button::K(AKey).into()));
+            expressions.push(blocking);
+            expressions.push(Expression::Tap(Button::K(BKey).into()));
+            let engine = engine(expressions, backend.clone()).unwrap();
+            let handle = std::thread::spawn(move || engine.start(1));
+            assert!(backend.wait_until(|events| events.len() == 2, Duration::from_secs(1)));
+            let halted_at = std::time::Instant::now();
+            backend.trigger_hotkey(EscapeKey);
+            handle.join().unwrap().unwrap();
+            // the executor stopped instead of being left behind
+            assert!(halted_at.elapsed() < Duration::from_millis(500));
+            assert!(!backend.is_listening(F2Key));
+            assert_eq!(backend.inputs().len(), 2);
+        }
+    }
+
+    #[test]
+    fn variables_updated_in_repeat() {
+        let backend = Arc::new(RecordingBackend::new());
+        let var = |name: &str| Box::new(Term::Variable(name.to_string()));
+        let mut expressions = header();
+        expressions.push(Expression::Let("x".to_string(), Term::int(100)));
+        expressions.push(Expression::Repeat(
+            Some(Term::int(3)),
+            vec![
+                Expression::Move(Term::Variable("x".to_string()), Term::float(50.5)),
+                Expression::Let(
+                    "x".to_string(),
+                    Term::Binary(var("x"), Operator::Add, Box::new(Term::int(10))),
+                ),
+            ],
+        ));
+        Engine::new(
+            expressions,
+            (1920, 1080),
+            backend.clone(),
+            Arc::new(SimulatedClock::new()),
+        )
+        .unwrap()
+        .start(1)
+        .unwrap();
+        assert_eq!(
+            backend.inputs(),
+            vec![
+                InputEvent::MoveAbs(100, 50),
+                InputEvent::MoveAbs(110, 50),
+                InputEvent::MoveAbs(120, 50),
+            ]
+        );
+    }
+
+    #[test]
+    fn runtime_error_stops_engine() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::Let("name".to_string(), Term::string("a")));
+        expressions.push(Expression::Sleep(Term::Variable("name".to_string())));
+        expressions.push(Expression::Tap(Button::K(AKey).into()));
+        let result = Engine::new(
+            expressions,
+            (1920, 1080),
+            backend.clone(),
+            Arc::new(SimulatedClock::new()),
+        )
+        .unwrap()
+        .start(1);
+        assert!(result.is_err());
+        assert!(backend.inputs().is_empty());
+    }
+
+    #[test]
+    fn if_tests_clipboard() {
+        let backend = Arc::new(RecordingBackend::new());
+        let mut expressions = header();
+        expressions.push(Expression::If(
+            Condition::ClipboardMatches(Pattern(regex::Regex::new(r"^\d+$").unwrap())),
+            vec![Expression::Tap(Button::K(VKey).into())],
+            vec![Expression::Tap(Button::K(EscapeKey).into())],
+        ));
+        let engine = |backend: Arc<RecordingBackend>| {
+            Engine::new(
+                expressions.clone(),
+                (1920, 1080),
+                backend,
+                Arc::new(SimulatedClock::new()),
+            )
+            .unwrap()
+            .start(1)
+            .unwrap();
+        };
+
+        backend.set_clipboard(Some("1234"));
+        engine(backend.clone());
+        assert_eq!(
+            backend.inputs(),
+            vec![InputEvent::KeyDown(VKey), InputEvent::KeyUp(VKey)]
+        );
+
+        backend.clear_events();
+        backend.set_clipboard(Some("12a"));
+        engine(backend.clone());
+        assert_eq!(
+            backend.inputs(),
+            vec![InputEvent::KeyDown(EscapeKey), InputEvent::KeyUp(EscapeKey)]
+        );
+    }
+
+    #[test]
+    fn call_binds_parameters() {
+        let backend = Arc::new(RecordingBackend::new());
+        let var = |name: &str| Term::Variable(name.to_string());
+        let function = Arc::new(Function {
+            name: "click".to_string(),
+            parameters: vec!["x".to_string()],
+            body: vec![
+                Expression::Move(var("x"), var("y")),
+                Expression::Tap(Button::M(LeftButton).into()),
+            ],
+        });
+        let mut expressions = header();
+        expressions.push(Expression::Let("x".to_string(), Term::int(1)));
+        expressions.push(Expression::Let("y".to_string(), Term::int(2)));
+        expressions.push(Expression::Call(function.clone(), vec![Term::int(10)]));
+        expressions.push(Expression::Call(
+            function,
+            vec![Term::Binary(
+                Box::new(var("x")),
+                Operator::Add,
+                Box::new(Term::int(20)),
+            )],
+        ));
+        expressions.push(Expression::Move(var("x"), var("y")));
+        Engine::new(
+            expressions,
+            (1920, 1080),
+            backend.clone(),
+            Arc::new(SimulatedClock::new()),
+        )
+        .unwrap()
+        .start(1)
+        .unwrap();
+        assert_eq!(
+            backend.inputs(),
+            vec![
+                InputEvent::MoveAbs(10, 2),
+                InputEvent::MouseDown(LeftButton),
+                InputEvent::MouseUp(LeftButton),
+                InputEvent::MoveAbs(21, 2),
+                InputEvent::MouseDown(LeftButton),
+                InputEvent::MouseUp(LeftButton),
+                InputEvent::MoveAbs(1, 2),
+            ]
+        );
     }
 }
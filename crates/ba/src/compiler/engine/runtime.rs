@@ -0,0 +1,224 @@
+use super::cancel::{CancellationToken, Cancelled};
+use crate::compiler::{
+    button::Button,
+    value::{Term, Value, Variables},
+};
+use inputbot::{Clock, InputBackend};
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+/// Everything an expression needs to be executed, shared by the executor and the binds
+#[derive(Clone)]
+pub struct Runtime {
+    pub backend: Arc<dyn InputBackend>,
+    pub clock: Arc<dyn Clock>,
+    /// delay waited after every executed expression
+    pub delay: Duration,
+    // host resolution divided by the script resolution
+    scale: (f64, f64),
+    token: CancellationToken,
+    paused: Arc<AtomicBool>,
+    // pressed by the script and not released yet, in the order they were pressed
+    held: Arc<Mutex<Vec<Button>>>,
+    variables: Arc<Mutex<Variables>>,
+    // parameters of the function being executed
+    locals: Variables,
+}
+
+impl Runtime {
+    // how often blocking expressions check whether the run was paused, resumed or
+    // cancelled, hence the longest a halted script keeps running
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    pub fn new(
+        backend: Arc<dyn InputBackend>,
+        clock: Arc<dyn Clock>,
+        delay: Duration,
+        host_resolution: (i32, i32),
+        script_resolution: (i32, i32),
+    ) -> Self {
+        Self {
+            backend,
+            clock,
+            delay,
+            scale: (
+                host_resolution.0 as f64 / script_resolution.0 as f64,
+                host_resolution.1 as f64 / script_resolution.1 as f64,
+            ),
+            token: CancellationToken::new(),
+            paused: Arc::new(AtomicBool::new(false)),
+            held: Arc::new(Mutex::new(Vec::new())),
+            variables: Arc::new(Mutex::new(Variables::new())),
+            locals: Variables::new(),
+        }
+    }
+
+    /// Cancels the run, every expression still executing stops within a polling interval
+    pub fn halt(&self) {
+        self.token.cancel();
+    }
+
+    #[cfg(test)]
+    pub fn is_halted(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Waits for a polling interval, which is not part of the script's timeline,
+    /// fails once the run is cancelled
+    pub fn poll(&self) -> Result<(), Cancelled> {
+        self.token.check()?;
+        self.clock.poll(Self::POLL_INTERVAL);
+        Ok(())
+    }
+
+    /// Pauses or resumes the execution, returns true if it is now paused
+    pub fn toggle_pause(&self) -> bool {
+        !self.paused.fetch_xor(true, Ordering::SeqCst)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Blocks while the execution is paused, fails once the run is cancelled
+    pub fn wait_while_paused(&self) -> Result<(), Cancelled> {
+        while self.is_paused() {
+            self.poll()?;
+        }
+        self.token.check()
+    }
+
+    /// Sleeps for the given duration of script time, the countdown stops while paused
+    pub fn sleep(&self, duration: Duration) -> Result<(), Cancelled> {
+        // a single deadline, the steps oversleeping the clock would otherwise add up
+        let mut deadline = self.clock.now() + duration;
+        loop {
+            let paused_at = self.is_paused().then(|| self.clock.now());
+            self.wait_while_paused()?;
+            if let Some(paused_at) = paused_at {
+                deadline += self.clock.now().saturating_sub(paused_at);
+            }
+            let remaining = deadline.saturating_sub(self.clock.now());
+            if remaining.is_zero() {
+                return Ok(());
+            }
+            self.clock.sleep(remaining.min(Self::POLL_INTERVAL));
+        }
+    }
+
+    /// Fails once the run is cancelled. The button is recorded before being pressed and the
+    /// lock is not held while the backend presses it, the panic hook calling `release_all`
+    /// would otherwise wait forever for a backend that panicked.
+    pub fn press(&self, button: Button) -> Result<(), Cancelled> {
+        {
+            let mut held = self.held.lock().unwrap_or_else(|err| err.into_inner());
+            self.token.check()?;
+            if !held.contains(&button) {
+                held.push(button);
+            }
+        }
+        button.press(self.backend.as_ref());
+        // halted in between, `release_all` may have released it before it was pressed
+        if self.token.is_cancelled()
+            && !self
+                .held
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .contains(&button)
+        {
+            button.release(self.backend.as_ref());
+            Err(Cancelled)?
+        }
+        Ok(())
+    }
+
+    pub fn release(&self, button: Button) {
+        button.release(self.backend.as_ref());
+        self.held
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .retain(|held| *held != button);
+    }
+
+    /// Releases every button still held by the script, in reverse order of their presses,
+    /// returns the released buttons
+    pub fn release_all(&self) -> Vec<Button> {
+        let held = std::mem::take(&mut *self.held.lock().unwrap_or_else(|err| err.into_inner()));
+        for button in held.iter().rev() {
+            tracing::warn!("Force-released '{}'", button);
+            button.release(self.backend.as_ref());
+        }
+        held
+    }
+
+    /// Parameters shadow the variables of the same name
+    pub fn evaluate(&self, term: &Term) -> anyhow::Result<Value> {
+        let variables = self.variables.lock().unwrap_or_else(|err| err.into_inner());
+        term.evaluate(&[&self.locals, &variables])
+    }
+
+    /// Runtime for the body of a function, only its own parameters are visible as locals
+    pub fn with_locals(&self, locals: Variables) -> Self {
+        Self {
+            locals,
+            ..self.clone()
+        }
+    }
+
+    pub fn set_variable(&self, name: &str, value: Value) {
+        self.variables
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(name.to_string(), value);
+    }
+
+    /// Converts a position of the script resolution to the host resolution
+    pub fn adapt_position(&self, x: i32, y: i32) -> (i32, i32) {
+        if self.scale == (1.0, 1.0) {
+            return (x, y);
+        }
+        let new_x = (x as f64 * self.scale.0).floor() as i32;
+        let new_y = (y as f64 * self.scale.1).floor() as i32;
+        tracing::trace!("Adapted position, ({x}, {y}) → ({new_x}, {new_y})");
+        (new_x, new_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Runtime;
+    use inputbot::{Clock, RecordingBackend, SimulatedClock};
+    use std::{sync::Arc, time::Duration};
+
+    // oversleeps every sleep by a millisecond, as the wall clock does
+    struct LateClock(SimulatedClock);
+
+    impl Clock for LateClock {
+        fn now(&self) -> Duration {
+            self.0.now()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.0.sleep(duration + Duration::from_millis(1))
+        }
+    }
+
+    #[test]
+    fn sleep_does_not_drift() {
+        let clock = Arc::new(LateClock(SimulatedClock::new()));
+        let runtime = Runtime::new(
+            Arc::new(RecordingBackend::new()),
+            clock.clone(),
+            Duration::ZERO,
+            (1920, 1080),
+            (1920, 1080),
+        );
+        runtime.sleep(Duration::from_secs(600)).unwrap();
+        assert_eq!(clock.now(), Duration::from_millis(600_001));
+    }
+}
@@ -1,50 +1,34 @@
-use oneshot::{Receiver, TryRecvError};
-use std::thread::JoinHandle;
+use inputbot::{HotkeyHandle, InputBackend};
+use std::sync::mpsc::Sender;
 
-use crate::compiler::button::Button;
+use super::event::EngineEvent;
+use crate::compiler::button::Hotkey;
 
+/// Keeps the global halt key registered, its press is sent to the main thread
 pub(super) struct Watcher {
-    receiver: Receiver<()>,
-    handle: JoinHandle<()>,
+    handle: HotkeyHandle,
 }
 
 impl Watcher {
-    pub(super) fn new(button: Button) -> anyhow::Result<Self> {
-        let (sender, receiver) = oneshot::channel::<()>();
-        let handle = button
-            .listen_once(move || {
-                tracing::info!("Halt key pressed");
-                match sender.send(()) {
-                    Ok(()) => tracing::debug!("Halt message sent"),
-                    Err(_) => {
-                        tracing::debug!(
-                            "Failed to send halt message as the receiver no longer exists"
-                        )
-                    }
-                };
-            })
-            .unwrap();
-        Ok(Self { receiver, handle })
-    }
-
-    pub(super) fn check(&self) -> bool {
-        match self.receiver.try_recv() {
-            Ok(()) => {
-                tracing::info!("Halt message received");
-                true
-            }
-            Err(TryRecvError::Disconnected) => {
-                tracing::error!("Watcher thread disconnected");
-                true
-            }
-            Err(TryRecvError::Empty) => false,
-        }
+    pub(super) fn new(
+        hotkey: Hotkey,
+        backend: &dyn InputBackend,
+        events: Sender<EngineEvent>,
+    ) -> anyhow::Result<Self> {
+        let handle = hotkey.listen_once(backend, move || {
+            tracing::info!("Halt key pressed");
+            match events.send(EngineEvent::Halt) {
+                Ok(()) => tracing::debug!("Halt message sent"),
+                Err(_) => {
+                    tracing::debug!("Failed to send halt message as the receiver no longer exists")
+                }
+            };
+        })?;
+        Ok(Self { handle })
     }
 
     pub(super) fn post_halt(self) {
-        match self.handle.join() {
-            Ok(()) => tracing::debug!("Watcher thread successfully joined"),
-            Err(err) => tracing::warn!("Watcher thread panicked '{:?}'", err),
-        }
+        tracing::debug!("Releasing the global halt key {:?}", self.handle.id());
+        self.handle.cancel();
     }
 }
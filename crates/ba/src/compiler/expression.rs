@@ -1,60 +1,141 @@
-use super::button::Button;
-use inputbot::{MouseWheel, get_clipboard_string};
-use std::io::Write;
+use super::{
+    button::{Chord, Hotkey},
+    condition::Condition,
+    engine::Runtime,
+    value::{Term, Variables},
+};
+use inputbot::KeyboardLayout;
+use std::{io::Write, sync::Arc};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
     Resolution((i32, i32)),
     DelayBetweenActions(u64),
-    GlobalHaltKey(Button),
-    Move((i32, i32)),
-    Tap(Button),
-    Press(Button),
-    Release(Button),
-    Sleep(f64),
-    Type(String),
+    GlobalHaltKey(Hotkey),
+    GlobalPauseKey(Hotkey),
+    /// Layout the names of the script were written for
+    KeyboardLayout(&'static KeyboardLayout),
+    Move(Term, Term),
+    Tap(Chord),
+    Press(Chord),
+    Release(Chord),
+    Sleep(Term),
+    Type(Term),
     Await,
-    AwaitKey(Button),
-    Bind(Button, Vec<Expression>),
-    Print(String),
+    AwaitKey(Hotkey),
+    Bind(Hotkey, Vec<Expression>),
+    Print(Term),
     PrintClipboard,
-    Scroll(i32),
+    Scroll(Term),
+    /// Repeats the block the given number of times, forever if none
+    Repeat(Option<Term>, Vec<Expression>),
+    Let(String, Term),
+    /// Executes the first block if the condition holds, the second one otherwise
+    If(Condition, Vec<Expression>, Vec<Expression>),
+    /// Executes the function with the given arguments
+    Call(Arc<Function>, Vec<Term>),
+}
+
+/// A function defined in the script, its calls are resolved at parse time
+#[derive(Debug, PartialEq)]
+pub struct Function {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: Vec<Expression>,
 }
 
 impl Expression {
-    pub(super) fn execute(&self) {
+    pub(super) fn execute(&self, runtime: &Runtime) -> anyhow::Result<()> {
+        let backend = runtime.backend.as_ref();
         match self {
             // Handled during engine creation
             Self::Resolution(_) => (),
             Self::DelayBetweenActions(_) => (),
             Self::GlobalHaltKey(_) => (),
+            Self::GlobalPauseKey(_) => (),
+            Self::KeyboardLayout(_) => (),
             Self::Bind(..) => (),
 
             // Handled directly
-            Self::Move(pos) => inputbot::MouseCursor::move_abs(pos.0, pos.1),
-            Self::Tap(button) => button.tap(),
-            Self::Press(button) => button.press(),
-            Self::Release(button) => button.release(),
-            Self::Sleep(float) => std::thread::sleep(std::time::Duration::from_secs_f64(*float)),
-            Self::Type(string) => inputbot::send_sequence(string),
+            Self::Move(x, y) => {
+                let (x, y) = runtime.adapt_position(
+                    runtime.evaluate(x)?.as_i32()?,
+                    runtime.evaluate(y)?.as_i32()?,
+                );
+                backend.move_abs(x, y)
+            }
+            Self::Tap(chord) => chord.tap(backend),
+            Self::Press(chord) => chord.press(runtime)?,
+            Self::Release(chord) => chord.release(runtime),
+            Self::Sleep(seconds) => {
+                let seconds = runtime.evaluate(seconds)?.as_f64()?;
+                runtime.sleep(std::time::Duration::try_from_secs_f64(seconds)?)?
+            }
+            Self::Type(term) => backend.send_sequence(&runtime.evaluate(term)?.to_string()),
             Self::Await => loop {
-                std::thread::sleep(std::time::Duration::from_secs(5))
+                runtime.poll()?
             },
-            Self::AwaitKey(button) => {
-                if let Err(err) = button.await_in_place() {
-                    tracing::warn!("{}", err);
-                }
-            }
-            Self::Print(string) => print_trace(string),
+            Self::AwaitKey(hotkey) => hotkey.await_in_place(runtime)?,
+            Self::Print(term) => print_trace(&runtime.evaluate(term)?.to_string()),
             Self::PrintClipboard => {
-                if let Some(string) = get_clipboard_string() {
+                if let Some(string) = backend.get_clipboard_string() {
                     print_trace(&string)
                 } else {
                     tracing::warn!("Failed to copy the clipboard's content");
                 }
             }
-            // see https://github.com/microsoft/win32metadata/issues/1865#issuecomment-1977365435
-            Self::Scroll(value) => MouseWheel::scroll_ver_unscaled(*value as u32),
+            Self::Scroll(amount) => backend.scroll_ver(runtime.evaluate(amount)?.as_i32()?),
+            Self::Repeat(count, expressions) => {
+                let count = match count {
+                    Some(count) => Some(runtime.evaluate(count)?.as_count()?),
+                    None => None,
+                };
+                let mut iteration = 0;
+                while count.is_none_or(|count| iteration < count) {
+                    // the body may never wait, e.g. when it only sets variables
+                    runtime.wait_while_paused().inspect_err(|_| {
+                        tracing::debug!("Repeat stopped after {} iteration(s)", iteration)
+                    })?;
+                    tracing::trace!("Repeat iteration {}", iteration + 1);
+                    execute_all(expressions, runtime)?;
+                    iteration += 1;
+                }
+            }
+            Self::If(condition, then, otherwise) => {
+                let holds = condition.evaluate(runtime)?;
+                tracing::trace!("Condition '{:?}' evaluated to {}", condition, holds);
+                execute_all(if holds { then } else { otherwise }, runtime)?;
+            }
+            Self::Call(function, arguments) => {
+                let locals = function
+                    .parameters
+                    .iter()
+                    .zip(arguments.iter())
+                    .map(|(parameter, argument)| {
+                        Ok((parameter.clone(), runtime.evaluate(argument)?))
+                    })
+                    .collect::<anyhow::Result<Variables>>()?;
+                tracing::trace!("Calling '{}' with {:?}", function.name, locals);
+                execute_all(&function.body, &runtime.with_locals(locals))
+                    .map_err(|err| err.context(format!("In function '{}'", function.name)))?;
+            }
+            Self::Let(name, term) => {
+                let value = runtime.evaluate(term)?;
+                tracing::trace!("${} = {:?}", name, value);
+                runtime.set_variable(name, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether executing the expression may block on an Await, nested blocks included
+    pub(super) fn awaits(&self) -> bool {
+        match self {
+            Self::Await | Self::AwaitKey(_) => true,
+            Self::Repeat(_, inner) => inner.iter().any(Self::awaits),
+            Self::If(_, then, otherwise) => then.iter().chain(otherwise).any(Self::awaits),
+            Self::Call(function, _) => function.body.iter().any(Self::awaits),
+            _ => false,
         }
     }
 
@@ -64,37 +145,21 @@ impl Expression {
             Self::Resolution(_)
                 | Self::DelayBetweenActions(_)
                 | Self::GlobalHaltKey(_)
+                | Self::GlobalPauseKey(_)
+                | Self::KeyboardLayout(_)
                 | Self::Bind(..)
         )
     }
 }
 
-pub fn adapt_expressions(
-    input: Vec<Expression>,
-    host_resolution: (i32, i32),
-    script_resolution: (i32, i32),
-) -> Vec<Expression> {
-    let width_ratio: f64 = host_resolution.0 as f64 / script_resolution.0 as f64;
-    let height_ratio: f64 = host_resolution.1 as f64 / script_resolution.1 as f64;
-    let modify_positions = (width_ratio != 1.0) | (height_ratio != 1.0);
-
-    input
-        .into_iter()
-        .filter(|expr| !expr.is_handled_at_init())
-        .map(|expr| match expr {
-            expr @ Expression::Move((x, y)) => {
-                if modify_positions {
-                    let new_x = (x as f64 * width_ratio).floor() as i32;
-                    let new_y = (y as f64 * height_ratio).floor() as i32;
-                    tracing::trace!("Adapted 'Move' expression, ({x}, {y}) → ({new_x}, {new_y})");
-                    Expression::Move((new_x, new_y))
-                } else {
-                    expr
-                }
-            }
-            other => other,
-        })
-        .collect()
+/// Executes the expressions in order, waiting the delay between actions after each one
+pub(super) fn execute_all(expressions: &[Expression], runtime: &Runtime) -> anyhow::Result<()> {
+    for expr in expressions.iter() {
+        runtime.wait_while_paused()?;
+        expr.execute(runtime)?;
+        runtime.sleep(runtime.delay)?;
+    }
+    Ok(())
 }
 
 pub fn print_trace(input: &str) {
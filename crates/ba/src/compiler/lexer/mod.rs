@@ -2,15 +2,14 @@ use rules::{Rule, get_rules};
 
 use crate::TK;
 
-use super::token::*;
+use super::{source::FileId, token::*};
 mod rules;
 
 #[derive(Debug)]
 pub struct Lexer<'input> {
     input: &'input str,
+    file: FileId,
     position: usize,
-    // for error accuracy
-    line: usize,
     // to place an EOI before EOF
     pre_eof: bool,
     eof: bool,
@@ -18,17 +17,25 @@ pub struct Lexer<'input> {
 }
 
 impl<'input> Lexer<'input> {
-    pub(super) fn new(input: &'input str) -> Self {
+    pub(super) fn new(input: &'input str, file: FileId) -> Self {
         Self {
             input,
+            file,
             position: 0,
-            line: 1,
             pre_eof: false,
             eof: false,
             rules: get_rules(),
         }
     }
 
+    fn span(&self, start: usize, end: usize) -> Span {
+        Span {
+            file: self.file,
+            start,
+            end,
+        }
+    }
+
     #[cfg(test)]
     pub(super) fn tokenize(&mut self) -> Vec<Token> {
         self.collect()
@@ -41,9 +48,6 @@ impl<'input> Lexer<'input> {
 
     fn valid_token(&mut self, input: &str) -> Option<Token> {
         let next = input.chars().next().unwrap();
-        if next == '\n' {
-            self.line += 1
-        }
         let (len, kind) = {
             // \n has to be treated seperatly
             if next.is_whitespace() && next != '\n' {
@@ -66,10 +70,7 @@ impl<'input> Lexer<'input> {
         self.position += len;
         Some(Token {
             kind,
-            span: Span {
-                start,
-                end: start + len,
-            },
+            span: self.span(start, start + len),
         })
     }
 
@@ -81,14 +82,12 @@ impl<'input> Lexer<'input> {
             .map(|(pos, _)| pos)
             .unwrap_or_else(|| input.len());
 
-        tracing::error!("Unknown token '{}' on line {}", &input[0..len], self.line);
+        // reported with its location by the parser
+        tracing::debug!("Unknown token '{}'", &input[0..len]);
         self.position = start + len;
         Token {
             kind: TK![Error],
-            span: Span {
-                start,
-                end: start + len,
-            },
+            span: self.span(start, start + len),
         }
     }
 }
@@ -105,20 +104,14 @@ impl<'input> Iterator for Lexer<'input> {
                 self.eof = true;
                 return Some(Token {
                     kind: TK![EOF],
-                    span: Span {
-                        start: self.position,
-                        end: self.position,
-                    },
+                    span: self.span(self.position, self.position),
                 });
             }
 
             self.pre_eof = true;
             Some(Token {
                 kind: TK![EOI],
-                span: Span {
-                    start: self.position,
-                    end: self.position,
-                },
+                span: self.span(self.position, self.position),
             })
         } else {
             Some(self.next_token(&self.input[self.position..]))
@@ -126,15 +119,50 @@ impl<'input> Iterator for Lexer<'input> {
     }
 }
 
+/// Whether the name is read as a single word, which key and mouse button names must be
+pub fn is_word(name: &str) -> bool {
+    matches!(
+        Lexer::new(name, FileId::MAIN).next(),
+        Some(token) if token.kind == TK![Word] && token.span.end == name.len()
+    )
+}
+
+/// Keyword the name is read as with some capitalization, words the parser gives a meaning
+/// to after a button are included
+pub fn keyword_of(name: &str) -> Option<&'static str> {
+    rules::KEYWORDS
+        .iter()
+        .chain(["passthrough"].iter())
+        .find(|keyword| keyword.eq_ignore_ascii_case(name))
+        .copied()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{Lexer, TokenKind};
+    use super::{FileId, Lexer, TokenKind, is_word, keyword_of, rules::KEYWORDS};
     use crate::TK;
 
+    #[test]
+    fn names() {
+        assert!(is_word("nr1"));
+        assert!(is_word("left_arrow"));
+        assert!(!is_word("flèchegauche"));
+        assert!(!is_word("1a"));
+        assert!(!is_word("a b"));
+        assert!(!is_word("let"));
+        assert_eq!(keyword_of("move"), Some("Move"));
+        assert_eq!(keyword_of("printclipboard"), Some("PrintClipboard"));
+        assert_eq!(keyword_of("passthrough"), Some("passthrough"));
+        assert_eq!(keyword_of("esc"), None);
+        for keyword in KEYWORDS {
+            assert!(!is_word(keyword), "'{}' is read as a word", keyword);
+        }
+    }
+
     #[test]
     fn short() {
         let input: &str = "// Comment abcd\ndefine RESOLUTION = 1920, 1080\nPress LMB\nSleep 4.3";
-        let mut lexer = Lexer::new(input);
+        let mut lexer = Lexer::new(input, FileId::MAIN);
         let token_kinds: Vec<TokenKind> = lexer
             .tokenize()
             .into_iter()
@@ -151,7 +179,10 @@ mod tests {
                 TK![ws],
                 TK![=],
                 TK![ws],
-                TK![Position],
+                TK![Float],
+                TK![,],
+                TK![ws],
+                TK![Float],
                 TK![EOI],
                 TK![Press],
                 TK![ws],
@@ -169,7 +200,7 @@ mod tests {
     #[test]
     fn medium() {
         let input: &str = "Bind NR1 {\n  Tap LMB\n  Sleep 0.1\n}\nAwait";
-        let mut lexer = Lexer::new(input);
+        let mut lexer = Lexer::new(input, FileId::MAIN);
         let token_kinds: Vec<TokenKind> = lexer
             .tokenize()
             .into_iter()
@@ -206,7 +237,7 @@ mod tests {
     #[test]
     fn long() {
         let input: &str = "// Comment\ndefine RESOLUTION=1920,1080\nMove\nTap\nPress LMB\nRelease LMB\nSleep 4.1\nType \"a simple test\"";
-        let mut lexer = Lexer::new(input);
+        let mut lexer = Lexer::new(input, FileId::MAIN);
         let token_kinds: Vec<TokenKind> = lexer
             .tokenize()
             .into_iter()
@@ -221,7 +252,9 @@ mod tests {
                 TK![ws],
                 TK![Word],
                 TK![=],
-                TK![Position],
+                TK![Float],
+                TK![,],
+                TK![Float],
                 TK![EOI],
                 TK![Move],
                 TK![EOI],
@@ -251,7 +284,7 @@ mod tests {
     #[test]
     fn misc() {
         let input: &str = "Await nr1\nBind nr2 {\n  Println \"hello world\"\n}\nSleep 0.5 // Comment on same line";
-        let mut lexer = Lexer::new(input);
+        let mut lexer = Lexer::new(input, FileId::MAIN);
         let token_kinds: Vec<TokenKind> = lexer
             .tokenize()
             .into_iter()
@@ -287,4 +320,37 @@ mod tests {
             ],
         )
     }
+
+    #[test]
+    fn arithmetic() {
+        let input: &str = "let x = ($y+2)*-3 / 4 // comment";
+        let mut lexer = Lexer::new(input, FileId::MAIN);
+        let token_kinds: Vec<TokenKind> = lexer
+            .tokenize()
+            .into_iter()
+            .map(|token| token.kind)
+            .filter(|kind| *kind != TK![ws])
+            .collect();
+        assert_eq!(
+            token_kinds,
+            vec![
+                TK![let],
+                TK![Word],
+                TK![=],
+                TK![LParen],
+                TK![Variable],
+                TK![+],
+                TK![Float],
+                TK![RParen],
+                TK![*],
+                TK![-],
+                TK![Float],
+                TK![/],
+                TK![Float],
+                TK![Comment],
+                TK![EOI],
+                TK![EOF],
+            ],
+        )
+    }
 }
@@ -25,11 +25,38 @@ fn match_regex(input: &str, re: &Regex) -> Option<usize> {
 
 // match '//' then anything except a new line 0 or more times until a newline is met
 static WORD_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^[a-zA-z][a-zA-z0-9_]*"#).unwrap());
-static POSITION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^\d+ *, *\d+"#).unwrap());
+static VARIABLE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^\$[a-zA-Z_][a-zA-Z0-9_]*"#).unwrap());
 static STRING_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(^")(\\"|\\\\|[^\\"\n])*(")"#).unwrap());
 static FLOAT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^((\d+(\.\d+)?)|(\.\d+))"#).unwrap());
 static COMMENT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"^(//[^\n]*)"#).unwrap());
 
+// keywords made of letters, a key or mouse button named after one of them could not be
+// written in a script with the keyword's capitalization
+pub(super) static KEYWORDS: [&str; 22] = [
+    "define",
+    "let",
+    "Move",
+    "Tap",
+    "Press",
+    "Release",
+    "Sleep",
+    "Type",
+    "Await",
+    "Bind",
+    "Print",
+    "Println",
+    "PrintClip",
+    "PrintClipboard",
+    "ScrollUp",
+    "ScrollDown",
+    "Repeat",
+    "If",
+    "Else",
+    "Function",
+    "Call",
+    "Include",
+];
+
 // the higher the rule the higher its importance
 pub(super) fn get_rules() -> Vec<Rule> {
     vec![
@@ -37,10 +64,54 @@ pub(super) fn get_rules() -> Vec<Rule> {
             kind: TK![def],
             matches: |input| match_keyword(input, "define"),
         },
+        Rule {
+            kind: TK![let],
+            matches: |input| match_keyword(input, "let"),
+        },
         Rule {
             kind: TK![=],
             matches: |input| match_keyword(input, "="),
         },
+        Rule {
+            kind: TK![+],
+            matches: |input| match_keyword(input, "+"),
+        },
+        Rule {
+            kind: TK![-],
+            matches: |input| match_keyword(input, "-"),
+        },
+        Rule {
+            kind: TK![*],
+            matches: |input| match_keyword(input, "*"),
+        },
+        Rule {
+            kind: TK![/],
+            matches: |input| match_keyword(input, "/"),
+        },
+        Rule {
+            kind: TK![==],
+            matches: |input| match_keyword(input, "=="),
+        },
+        Rule {
+            kind: TK![!=],
+            matches: |input| match_keyword(input, "!="),
+        },
+        Rule {
+            kind: TK![<],
+            matches: |input| match_keyword(input, "<"),
+        },
+        Rule {
+            kind: TK![<=],
+            matches: |input| match_keyword(input, "<="),
+        },
+        Rule {
+            kind: TK![>],
+            matches: |input| match_keyword(input, ">"),
+        },
+        Rule {
+            kind: TK![>=],
+            matches: |input| match_keyword(input, ">="),
+        },
         Rule {
             kind: TK![Move],
             matches: |input| match_keyword(input, "Move"),
@@ -97,6 +168,30 @@ pub(super) fn get_rules() -> Vec<Rule> {
             kind: TK![ScrollDown],
             matches: |input| match_keyword(input, "ScrollDown"),
         },
+        Rule {
+            kind: TK![Repeat],
+            matches: |input| match_keyword(input, "Repeat"),
+        },
+        Rule {
+            kind: TK![If],
+            matches: |input| match_keyword(input, "If"),
+        },
+        Rule {
+            kind: TK![Else],
+            matches: |input| match_keyword(input, "Else"),
+        },
+        Rule {
+            kind: TK![Function],
+            matches: |input| match_keyword(input, "Function"),
+        },
+        Rule {
+            kind: TK![Call],
+            matches: |input| match_keyword(input, "Call"),
+        },
+        Rule {
+            kind: TK![Include],
+            matches: |input| match_keyword(input, "Include"),
+        },
         Rule {
             kind: TK![,],
             matches: |input| match_keyword(input, ","),
@@ -109,6 +204,14 @@ pub(super) fn get_rules() -> Vec<Rule> {
             kind: TK![RBrace],
             matches: |input| match_keyword(input, "}"),
         },
+        Rule {
+            kind: TK![LParen],
+            matches: |input| match_keyword(input, "("),
+        },
+        Rule {
+            kind: TK![RParen],
+            matches: |input| match_keyword(input, ")"),
+        },
         Rule {
             kind: TK![EOI],
             matches: |input| match_keyword(input, ";"),
@@ -122,8 +225,8 @@ pub(super) fn get_rules() -> Vec<Rule> {
             matches: |input| match_regex(input, &WORD_RE),
         },
         Rule {
-            kind: TK![Position],
-            matches: |input| match_regex(input, &POSITION_RE),
+            kind: TK![Variable],
+            matches: |input| match_regex(input, &VARIABLE_RE),
         },
         Rule {
             kind: TK![String],
@@ -1,12 +1,22 @@
 mod button;
+mod condition;
+mod context;
+mod diagnostic;
 mod engine;
 mod expression;
 mod lexer;
 mod parser;
+mod source;
 mod token;
+mod value;
 
 use token::*;
 
 // exports
+pub use button::Button;
+pub use context::{CompileContext, Layer};
 pub use engine::Engine;
+pub use expression::Expression;
+pub use lexer::{is_word, keyword_of};
 pub use parser::Parser;
+pub use source::SourceMap;
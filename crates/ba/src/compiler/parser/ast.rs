@@ -1,27 +1,77 @@
-use crate::compiler::{Span, Token, button::Button};
+use crate::compiler::{
+    Span, Token,
+    button::Button,
+    context::CompileContext,
+    diagnostic::{Diagnostic, codes},
+    value::Value,
+};
 
 // TokenKind check done by consume
-pub(super) fn token_to_button(token: Token, input: &str) -> anyhow::Result<Button> {
+pub(super) fn token_to_button(
+    token: Token,
+    input: &str,
+    context: &CompileContext,
+) -> Result<Button, Diagnostic> {
     let input = &input[token.span];
-    Button::try_from(input)
+    context.button(input).map_err(|err| {
+        Diagnostic::error(codes::UNKNOWN_BUTTON, err.to_string(), token.span).with_help(
+            "names are defined in keymap.json and mousemap.json, of the application or of the user",
+        )
+    })
 }
 
-pub(super) fn token_to_position(token: Token, input: &str) -> anyhow::Result<(i32, i32)> {
-    let input = &input[token.span];
-    // unwrapping because regex rules
-    let (width, height) = input.split_once(',').unwrap();
-    Ok((width.trim().parse()?, height.trim().parse()?))
-}
-
-pub(super) fn token_to_string(token: Token, input: &str) -> anyhow::Result<String> {
+pub(super) fn token_to_string(token: Token, input: &str) -> Result<String, Diagnostic> {
     let span_without_quotes = Span {
         start: token.span.start + 1,
         end: token.span.end - 1,
+        ..token.span
     };
-    Ok(input[span_without_quotes].to_string())
+    // the lexer only accepts the escape sequences \" and \\
+    let mut string = String::new();
+    let mut chars = input[span_without_quotes].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => string.extend(chars.next()),
+            c => string.push(c),
+        }
+    }
+    Ok(string)
+}
+
+pub(super) fn token_to_int(token: Token, input: &str) -> Result<i32, Diagnostic> {
+    let input = &input[token.span];
+    input.parse().map_err(|err| {
+        Diagnostic::error(
+            codes::INVALID_NUMBER,
+            format!("Invalid whole number '{}', {}", input, err),
+            token.span,
+        )
+    })
+}
+
+/// Integers are told apart from floats by the presence of a decimal point
+pub(super) fn token_to_number(token: Token, input: &str) -> Result<Value, Diagnostic> {
+    if input[token.span].contains('.') {
+        Ok(Value::Float(token_to_float(token, input)?))
+    } else {
+        let text = &input[token.span];
+        text.parse().map(Value::Int).map_err(|err| {
+            Diagnostic::error(
+                codes::INVALID_NUMBER,
+                format!("Invalid number '{}', {}", text, err),
+                token.span,
+            )
+        })
+    }
 }
 
-pub(super) fn token_to_float(token: Token, input: &str) -> anyhow::Result<f64> {
+pub(super) fn token_to_float(token: Token, input: &str) -> Result<f64, Diagnostic> {
     let input = &input[token.span];
-    Ok(input.parse()?)
+    input.parse().map_err(|err| {
+        Diagnostic::error(
+            codes::INVALID_NUMBER,
+            format!("Invalid number '{}', {}", input, err),
+            token.span,
+        )
+    })
 }
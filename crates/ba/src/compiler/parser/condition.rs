@@ -0,0 +1,161 @@
+use super::{
+    Parser,
+    ast::{token_to_button, token_to_string},
+    unexpected,
+};
+use crate::{
+    TK,
+    compiler::{
+        Token,
+        button::Button,
+        condition::{Comparison, Condition, Pattern},
+        diagnostic::{Diagnostic, codes},
+        expression::Expression,
+    },
+};
+
+impl<'input, I> Parser<'input, I>
+where
+    I: Iterator<Item = Token>,
+{
+    /// Parses what follows an `If`, including the `Else` branches
+    pub(super) fn parse_if(&mut self) -> Result<Expression, Diagnostic> {
+        let condition = self.parse_condition()?;
+        let lbrace = self.consume(TK![LBrace])?;
+        let then = self.parse_block(
+            lbrace,
+            "if",
+            codes::INVALID_IN_CONDITION,
+            &[TK![def], TK![Bind], TK![Function], TK![Include]],
+        )?;
+
+        // `Else` can also start on the line after the closing brace
+        while self.peek() == TK![EOI] {
+            self.next();
+        }
+        let otherwise = if self.peek() == TK![Else] {
+            self.consume(TK![Else])?;
+            if self.peek() == TK![If] {
+                self.consume(TK![If])?;
+                vec![self.parse_if()?]
+            } else {
+                let lbrace = self.consume(TK![LBrace])?;
+                self.parse_block(
+                    lbrace,
+                    "else",
+                    codes::INVALID_IN_CONDITION,
+                    &[TK![def], TK![Bind], TK![Function], TK![Include]],
+                )?
+            }
+        } else {
+            Vec::new()
+        };
+
+        Ok(Expression::If(condition, then, otherwise))
+    }
+
+    fn parse_condition(&mut self) -> Result<Condition, Diagnostic> {
+        if let Some(token) = self.tokens.peek().copied()
+            && token.kind == TK![Word]
+        {
+            self.next();
+            return match self.text(token) {
+                "pressed" => {
+                    let button =
+                        token_to_button(self.consume(TK![Word])?, self.input, self.context)?;
+                    Ok(Condition::Pressed(button))
+                }
+                "toggled" => {
+                    let key_token = self.consume(TK![Word])?;
+                    match token_to_button(key_token, self.input, self.context)? {
+                        Button::K(key) => Ok(Condition::Toggled(key)),
+                        Button::M(_) => Err(Diagnostic::error(
+                            codes::INVALID_CONDITION,
+                            format!(
+                                "The mouse button '{}' cannot be toggled",
+                                self.text(key_token)
+                            ),
+                            key_token.span,
+                        )
+                        .with_help("use 'pressed' to test mouse buttons")),
+                    }
+                }
+                "clipboard" => self.parse_clipboard_condition(),
+                other => Err(Diagnostic::error(
+                    codes::INVALID_CONDITION,
+                    format!("Unknown condition '{}'", other),
+                    token.span,
+                )
+                .with_help(
+                    "expected 'pressed <button>', 'toggled <key>', 'clipboard == <value>', \
+                    'clipboard matches <regex>' or a comparison such as '$x > 5'",
+                )),
+            };
+        }
+
+        let lhs = self.parse_term()?;
+        let comparison = self.parse_comparison()?;
+        let rhs = self.parse_term()?;
+        Ok(Condition::Compare(lhs, comparison, rhs))
+    }
+
+    // `clipboard` already consumed
+    fn parse_clipboard_condition(&mut self) -> Result<Condition, Diagnostic> {
+        if let Some(token) = self.tokens.peek().copied()
+            && token.kind == TK![Word]
+            && self.text(token) == "matches"
+        {
+            self.next();
+            let pattern_token = self.consume(TK![String])?;
+            let pattern = token_to_string(pattern_token, self.input)?;
+            return regex::Regex::new(&pattern)
+                .map(|regex| Condition::ClipboardMatches(Pattern(regex)))
+                .map_err(|err| {
+                    Diagnostic::error(
+                        codes::INVALID_REGEX,
+                        format!("Invalid regex '{}'", pattern),
+                        pattern_token.span,
+                    )
+                    .with_help(err.to_string())
+                });
+        }
+
+        let comparison = self.parse_comparison()?;
+        let term = self.parse_term()?;
+        Ok(Condition::Clipboard(comparison, term))
+    }
+
+    fn parse_comparison(&mut self) -> Result<Comparison, Diagnostic> {
+        let token = self.next().ok_or_else(|| {
+            Diagnostic::error(
+                codes::UNEXPECTED_EOF,
+                "Expected a comparison, but the file ended",
+                self.eof_span(),
+            )
+        })?;
+        match token.kind {
+            TK![==] => Ok(Comparison::Eq),
+            TK![!=] => Ok(Comparison::NotEq),
+            TK![<] => Ok(Comparison::Lt),
+            TK![<=] => Ok(Comparison::LtEq),
+            TK![>] => Ok(Comparison::Gt),
+            TK![>=] => Ok(Comparison::GtEq),
+            // `=` is the most likely typo
+            TK![=] => Err(Diagnostic::error(
+                codes::UNEXPECTED_TOKEN,
+                "Expected a comparison, found '='",
+                token.span,
+            )
+            .with_help("equality is tested with '==', '=' is only used by 'let' and 'define'")),
+            TK![Error] => Err(unexpected(token, TK![==], self.input)),
+            _ => Err(Diagnostic::error(
+                codes::UNEXPECTED_TOKEN,
+                format!(
+                    "Expected one of '==', '!=', '<', '<=', '>', '>=', found '{}'",
+                    token.kind
+                ),
+                token.span,
+            )),
+        }
+    }
+}
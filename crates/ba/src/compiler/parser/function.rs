@@ -0,0 +1,133 @@
+use super::Parser;
+use crate::{
+    TK,
+    compiler::{
+        Token,
+        diagnostic::{Diagnostic, codes},
+        expression::{Expression, Function},
+    },
+};
+use std::{collections::HashSet, sync::Arc};
+
+impl<'input, I> Parser<'input, I>
+where
+    I: Iterator<Item = Token>,
+{
+    /// Parses what follows `Function` and registers the function for the following calls
+    pub(super) fn parse_function(&mut self) -> Result<(), Diagnostic> {
+        let name_token = self.consume(TK![Word])?;
+        let name = self.text(name_token).to_string();
+        if self.functions.contains_key(&name) {
+            Err(Diagnostic::error(
+                codes::DUPLICATE_DEFINITION,
+                format!("Function '{}' is already defined", name),
+                name_token.span,
+            ))?
+        }
+
+        self.consume(TK![LParen])?;
+        let mut parameters: Vec<String> = Vec::new();
+        while self.peek() != TK![RParen] {
+            if !parameters.is_empty() {
+                self.consume(TK![,])?;
+            }
+            let token = self.consume(TK![Word])?;
+            let parameter = self.text(token).to_string();
+            if parameters.contains(&parameter) {
+                Err(Diagnostic::error(
+                    codes::DUPLICATE_DEFINITION,
+                    format!("Parameter '{}' is already declared", parameter),
+                    token.span,
+                ))?
+            }
+            parameters.push(parameter);
+        }
+        self.consume(TK![RParen])?;
+        let lbrace = self.consume(TK![LBrace])?;
+
+        // parameters are only visible inside the body
+        let outer_parameters =
+            std::mem::replace(&mut self.parameters, HashSet::from_iter(parameters.clone()));
+        let shadowing: Vec<String> = parameters
+            .iter()
+            .filter(|parameter| self.variables.insert(parameter.to_string()))
+            .cloned()
+            .collect();
+        let body = self.parse_block(
+            lbrace,
+            "function",
+            codes::INVALID_IN_FUNCTION,
+            &[TK![def], TK![Bind], TK![Function], TK![Include]],
+        );
+        self.parameters = outer_parameters;
+        for parameter in shadowing.iter() {
+            self.variables.remove(parameter);
+        }
+
+        tracing::trace!("Defined function '{}{:?}'", name, parameters);
+        self.functions.insert(
+            name.clone(),
+            Arc::new(Function {
+                name,
+                parameters,
+                body: body?,
+            }),
+        );
+        Ok(())
+    }
+
+    /// Parses what follows `Call`, the function must already be defined
+    pub(super) fn parse_call(&mut self) -> Result<Expression, Diagnostic> {
+        let name_token = self.consume(TK![Word])?;
+        let name = self.text(name_token);
+
+        self.consume(TK![LParen])?;
+        let mut arguments = Vec::new();
+        while self.peek() != TK![RParen] {
+            if !arguments.is_empty() {
+                self.consume(TK![,])?;
+            }
+            arguments.push(self.parse_term()?);
+        }
+        let rparen = self.consume(TK![RParen])?;
+        self.consume(TK![EOI])?;
+
+        let function = self.functions.get(name).cloned().ok_or_else(|| {
+            Diagnostic::error(
+                codes::UNDEFINED_FUNCTION,
+                format!("Function '{}' is not defined", name),
+                name_token.span,
+            )
+            .with_help("functions must be defined before being called and cannot call themselves")
+        })?;
+        if arguments.len() != function.parameters.len() {
+            Err(Diagnostic::error(
+                codes::ARGUMENT_COUNT,
+                format!(
+                    "Function '{}' takes {} argument(s) but {} were given",
+                    name,
+                    function.parameters.len(),
+                    arguments.len()
+                ),
+                name_token.span.to(rparen.span),
+            )
+            .with_help(format!(
+                "'{}' is defined as '{}({})'",
+                name,
+                name,
+                function.parameters.join(", ")
+            )))?
+        }
+
+        if self.in_bind && function.body.iter().any(Expression::awaits) {
+            Err(Diagnostic::error(
+                codes::INVALID_IN_BIND,
+                format!("Cannot call '{}' inside bind as it awaits", name),
+                name_token.span,
+            )
+            .with_help("a bind must never block, call the function outside of the bind"))?
+        }
+
+        Ok(Expression::Call(function, arguments))
+    }
+}
@@ -0,0 +1,124 @@
+use super::{Parser, TokenIter, ast::token_to_string};
+use crate::{
+    TK,
+    compiler::{
+        Span, Token,
+        diagnostic::{Diagnostic, codes},
+        expression::Expression,
+    },
+};
+
+impl<'input, I> Parser<'input, I>
+where
+    I: Iterator<Item = Token>,
+{
+    /// Parses `Include "path"` and the included file, whose expressions take its place,
+    /// a file already included is skipped
+    pub(super) fn parse_include(&mut self) -> Result<Vec<Expression>, Diagnostic> {
+        self.consume(TK![Include])?;
+        let path_token = self.consume(TK![String])?;
+        let name = token_to_string(path_token, self.input)?;
+        self.consume(TK![EOI])?;
+
+        let root = self.root.clone().ok_or_else(|| {
+            Diagnostic::error(
+                codes::INCLUDE_FAILED,
+                format!("Cannot include '{}' outside of an application folder", name),
+                path_token.span,
+            )
+        })?;
+        let failed = |err: std::io::Error| {
+            Diagnostic::error(
+                codes::INCLUDE_FAILED,
+                format!("Failed to read the included file '{}'", name),
+                path_token.span,
+            )
+            .with_help(format!(
+                "paths are relative to the application folder, {}",
+                err
+            ))
+        };
+        let path = root.join(&name).canonicalize().map_err(failed)?;
+        if !path.starts_with(&root) {
+            Err(Diagnostic::error(
+                codes::INCLUDE_OUTSIDE,
+                format!(
+                    "Cannot include '{}' from outside the application folder",
+                    name
+                ),
+                path_token.span,
+            )
+            .with_help(format!(
+                "only the files of '{}' can be included",
+                root.display()
+            )))?
+        }
+
+        if let Some(position) = self.includes.iter().position(|include| *include == path) {
+            let chain: Vec<String> = self.includes[position..]
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|include| {
+                    include
+                        .strip_prefix(&root)
+                        .unwrap_or(include)
+                        .display()
+                        .to_string()
+                })
+                .collect();
+            Err(Diagnostic::error(
+                codes::INCLUDE_CYCLE,
+                format!("Including '{}' creates a cycle", name),
+                path_token.span,
+            )
+            .with_help(chain.join(" -> ")))?
+        }
+
+        // e.g. two included files both including the same one
+        if !self.included.insert(path.clone()) {
+            tracing::trace!("Skipped '{}', already included", name);
+            return Ok(Vec::new());
+        }
+
+        let content = std::fs::read_to_string(&path).map_err(failed)?;
+        let file = self.sources.add(name.clone(), content.clone());
+
+        let span = tracing::span!(tracing::Level::TRACE, "Include Parsing", name);
+        let _guard = span.enter();
+        let mut included = self.child(&content, file, TokenIter::new(&content, file));
+        included.includes.push(path);
+        let expressions = included.parse_all();
+        self.absorb(included);
+        Ok(expressions)
+    }
+
+    /// Registers a definition, it can be repeated with the same value so that
+    /// included files can declare what they rely on
+    pub(super) fn define(
+        &mut self,
+        name: String,
+        expression: Expression,
+        span: Span,
+    ) -> Result<Option<Expression>, Diagnostic> {
+        match self.defines.get(&name) {
+            None => {
+                self.defines.insert(name, (expression.clone(), span));
+                Ok(Some(expression))
+            }
+            Some((first, _)) if *first == expression => {
+                tracing::trace!("Merged repeated definition of '{}'", name);
+                Ok(None)
+            }
+            Some((_, first_span)) => Err(Diagnostic::error(
+                codes::CONFLICTING_DEFINITION,
+                format!("Conflicting definition of '{}'", name),
+                span,
+            )
+            .with_help(format!(
+                "'{}' is first defined with another value at {}",
+                name,
+                self.sources.location(*first_span)
+            ))),
+        }
+    }
+}
@@ -1,24 +1,105 @@
-use super::{Token, TokenKind, expression::Expression, lexer::Lexer};
+use super::{
+    Span, Token, TokenKind,
+    button::{Button, Chord, Hotkey},
+    context::CompileContext,
+    diagnostic::{Diagnostic, codes},
+    expression::{Expression, Function},
+    lexer::Lexer,
+    source::{FileId, SourceMap},
+    value::{Operator, Term},
+};
 use crate::TK;
-use ast::{token_to_button, token_to_float, token_to_position, token_to_string};
-use std::iter::Peekable;
+use ast::{token_to_button, token_to_float, token_to_int, token_to_string};
+use inputbot::{KeyboardLayout, Modifiers};
+use std::{
+    collections::{HashMap, HashSet},
+    iter::Peekable,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 mod ast;
+mod condition;
+mod function;
+mod include;
+mod term;
 
 pub struct Parser<'input, I>
 where
     I: Iterator<Item = Token>,
 {
     input: &'input str,
+    // names of the keys and mouse buttons
+    context: &'input CompileContext,
+    // file of the input in `sources`
+    file: FileId,
     tokens: Peekable<I>,
+    // kind of the last token taken from `tokens`
+    last: Option<TokenKind>,
+    // every problem found so far, parsing goes on after an error
+    diagnostics: Vec<Diagnostic>,
+    // variables declared so far, shared with the blocks
+    variables: HashSet<String>,
+    // parameters of the function being parsed
+    parameters: HashSet<String>,
+    // functions defined so far, shared with the blocks
+    functions: HashMap<String, Arc<Function>>,
+    // definitions found so far with their location, shared with the included files
+    defines: HashMap<String, (Expression, Span)>,
+    // every file read so far, to render the diagnostics
+    sources: SourceMap,
+    // application folder the includes are resolved from
+    root: Option<PathBuf>,
+    // canonical paths of the files being parsed, from the main file to this one
+    includes: Vec<PathBuf>,
+    // canonical paths of every file included so far, shared with the included files
+    included: HashSet<PathBuf>,
+    // set in the body of a bind, whose callback must never block
+    in_bind: bool,
 }
 
 impl<'input> Parser<'input, TokenIter<'input>> {
-    pub fn new(input: &'input str) -> Parser<'input, TokenIter<'input>> {
-        Parser {
+    pub fn new(
+        input: &'input str,
+        context: &'input CompileContext,
+    ) -> Parser<'input, TokenIter<'input>> {
+        let mut parser = Parser::from_tokens(
             input,
-            tokens: TokenIter::new(input).peekable(),
-        }
+            context,
+            FileId::MAIN,
+            TokenIter::new(input, FileId::MAIN),
+        );
+        parser.sources.add("main.ba", input);
+        parser
+    }
+
+    /// Resolves the includes relative to the application folder `root`,
+    /// without it every `Include` is reported
+    pub fn with_root(mut self, root: &Path) -> Self {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        self.includes.push(root.join("main.ba"));
+        self.root = Some(root);
+        self
+    }
+
+    /// Layout selected by the KEYBOARD_LAYOUT definition of a main file, if any,
+    /// read ahead as its names are needed to parse the main file
+    pub fn keyboard_layout_of(input: &str) -> Option<&'static KeyboardLayout> {
+        let tokens: Vec<Token> = TokenIter::new(input, FileId::MAIN).collect();
+        tokens.windows(4).find_map(|window| match window {
+            [def, name, assign, string]
+                if def.kind == TK![def]
+                    && name.kind == TK![Word]
+                    && name.text(input).eq_ignore_ascii_case("KEYBOARD_LAYOUT")
+                    && assign.kind == TK![=]
+                    && string.kind == TK![String] =>
+            {
+                token_to_string(*string, input)
+                    .ok()
+                    .and_then(|name| KeyboardLayout::by_name(&name))
+            }
+            _ => None,
+        })
     }
 }
 
@@ -26,6 +107,68 @@ impl<'input, I> Parser<'input, I>
 where
     I: Iterator<Item = Token>,
 {
+    fn from_tokens(
+        input: &'input str,
+        context: &'input CompileContext,
+        file: FileId,
+        tokens: I,
+    ) -> Self {
+        Parser {
+            input,
+            context,
+            file,
+            tokens: tokens.peekable(),
+            last: None,
+            diagnostics: Vec::new(),
+            variables: HashSet::new(),
+            parameters: HashSet::new(),
+            functions: HashMap::new(),
+            defines: HashMap::new(),
+            sources: SourceMap::default(),
+            root: None,
+            includes: Vec::new(),
+            included: HashSet::new(),
+            in_bind: false,
+        }
+    }
+
+    /// Every file read by the parser, the main file first
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    // Parser of a block or an included file, it shares the declarations of this one
+    // until they are given back by `absorb`.
+    fn child<'a, J>(&mut self, input: &'a str, file: FileId, tokens: J) -> Parser<'a, J>
+    where
+        'input: 'a,
+        J: Iterator<Item = Token>,
+    {
+        let mut child = Parser::from_tokens(input, self.context, file, tokens);
+        child.variables = std::mem::take(&mut self.variables);
+        child.parameters = self.parameters.clone();
+        child.functions = std::mem::take(&mut self.functions);
+        child.defines = std::mem::take(&mut self.defines);
+        child.sources = std::mem::take(&mut self.sources);
+        child.root = self.root.clone();
+        child.includes = self.includes.clone();
+        child.included = std::mem::take(&mut self.included);
+        child.in_bind = self.in_bind;
+        child
+    }
+
+    fn absorb<J>(&mut self, mut child: Parser<'_, J>)
+    where
+        J: Iterator<Item = Token>,
+    {
+        self.diagnostics.append(&mut child.diagnostics);
+        self.variables = child.variables;
+        self.functions = child.functions;
+        self.defines = child.defines;
+        self.sources = child.sources;
+        self.included = child.included;
+    }
+
     // Get the source text of a token.
     fn text(&self, token: Token) -> &'input str {
         token.text(self.input)
@@ -41,161 +184,435 @@ where
 
     // Get the next token.
     fn next(&mut self) -> Option<Token> {
-        self.tokens.next()
+        let token = self.tokens.next();
+        self.last = token.map(|token| token.kind);
+        token
+    }
+
+    // Zero-width span at the end of the input.
+    fn eof_span(&self) -> Span {
+        Span {
+            file: self.file,
+            start: self.input.len(),
+            end: self.input.len(),
+        }
     }
 
-    fn consume(&mut self, expected: TokenKind) -> anyhow::Result<Token> {
-        let token = self.next().ok_or(anyhow::anyhow!(
-            "Expected to consume '{}', but there was no next token",
-            expected
-        ))?;
+    fn consume(&mut self, expected: TokenKind) -> Result<Token, Diagnostic> {
+        let token = self.next().ok_or_else(|| {
+            Diagnostic::error(
+                codes::UNEXPECTED_EOF,
+                format!("Expected '{}', but the file ended", expected),
+                self.eof_span(),
+            )
+        })?;
         if token.kind != expected {
-            Err(anyhow::anyhow!(
-                "Expected to consume '{}', but found '{}'",
-                expected,
-                token.kind
-            ))?;
+            Err(unexpected(token, expected, self.input))?;
         }
         Ok(token)
     }
 
-    pub fn process(&mut self) -> anyhow::Result<Vec<Expression>> {
+    // Parses buttons joined with `+` along with their token, a button cannot appear twice.
+    fn parse_buttons(&mut self) -> Result<Vec<(Button, Token)>, Diagnostic> {
+        let token = self.consume(TK![Word])?;
+        let mut buttons = vec![(token_to_button(token, self.input, self.context)?, token)];
+        while self.peek() == TK![+] {
+            self.consume(TK![+])?;
+            let token = self.consume(TK![Word])?;
+            let button = token_to_button(token, self.input, self.context)?;
+            if buttons.iter().any(|(other, _)| *other == button) {
+                Err(Diagnostic::error(
+                    codes::DUPLICATE_BUTTON,
+                    format!("'{}' appears twice in the chord", self.text(token)),
+                    token.span,
+                ))?
+            }
+            buttons.push((button, token));
+        }
+        Ok(buttons)
+    }
+
+    fn parse_chord(&mut self) -> Result<Chord, Diagnostic> {
+        let buttons = self.parse_buttons()?;
+        Ok(Chord(
+            buttons.into_iter().map(|(button, _)| button).collect(),
+        ))
+    }
+
+    // Parses modifier keys followed by the button triggering the hotkey, e.g. `ctrl+alt+nr1`.
+    fn parse_hotkey(&mut self) -> Result<Hotkey, Diagnostic> {
+        let mut buttons = self.parse_buttons()?;
+        // parse_buttons returns at least one button
+        let (button, button_token) = buttons.pop().unwrap();
+        if matches!(button, Button::M(_))
+            && let Some((_, token)) = buttons.first()
+        {
+            Err(Diagnostic::error(
+                codes::INVALID_HOTKEY,
+                format!(
+                    "Modifiers cannot be combined with the mouse button '{}'",
+                    self.text(button_token)
+                ),
+                token.span.to(button_token.span),
+            )
+            .with_help("mouse buttons are bound on their own, e.g. 'Bind x1 { ... }'"))?
+        }
+        let mut modifiers = Modifiers::default();
+        for (modifier, token) in buttons {
+            let modifier = match modifier {
+                Button::K(key) => Modifiers::from_key(key),
+                Button::M(_) => None,
+            };
+            modifiers = modifiers.union(modifier.ok_or_else(|| {
+                Diagnostic::error(
+                    codes::INVALID_HOTKEY,
+                    format!("'{}' is not a modifier key", self.text(token)),
+                    token.span,
+                )
+                .with_help(
+                    "hotkeys are made of modifiers (ctrl, alt, shift, super) followed by \
+                    a single key, e.g. 'ctrl+alt+nr1'",
+                )
+            })?);
+        }
+        // mouse buttons are swallowed unless followed by `passthrough`
+        let passthrough = match self.tokens.peek().copied() {
+            Some(token) if token.kind == TK![Word] && self.text(token) == "passthrough" => {
+                self.next();
+                if let Button::K(_) = button {
+                    Err(Diagnostic::error(
+                        codes::INVALID_HOTKEY,
+                        format!(
+                            "The key '{}' cannot be passed through",
+                            self.text(button_token)
+                        ),
+                        button_token.span.to(token.span),
+                    )
+                    .with_help("only mouse buttons can reach other applications once bound"))?
+                }
+                true
+            }
+            _ => false,
+        };
+        Ok(Hotkey {
+            modifiers,
+            button,
+            passthrough,
+        })
+    }
+
+    /// Parses the whole input, returns every diagnostic found if any
+    pub fn process(&mut self) -> Result<Vec<Expression>, Vec<Diagnostic>> {
+        let expressions = self.parse_all();
+        if self.diagnostics.is_empty() {
+            Ok(expressions)
+        } else {
+            Err(std::mem::take(&mut self.diagnostics))
+        }
+    }
+
+    fn parse_all(&mut self) -> Vec<Expression> {
         let mut expressions: Vec<Expression> = Vec::new();
-        while let Some(expr) = self.parse_expression()? {
-            tracing::trace!("Parsed expression '{:?}'", &expr);
-            expressions.push(expr);
+        loop {
+            let parsed = match self.peek() {
+                TK![EOF] => break,
+                TK![Include] => self.parse_include(),
+                _ => self.parse_expression().map(Vec::from_iter),
+            };
+            match parsed {
+                Ok(parsed) => {
+                    for expr in parsed {
+                        tracing::trace!("Parsed expression '{:?}'", &expr);
+                        expressions.push(expr);
+                    }
+                }
+                Err(diagnostic) => {
+                    tracing::trace!("Recovering from '{}'", &diagnostic);
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize();
+                }
+            }
+        }
+        expressions
+    }
+
+    // Skips the rest of a faulty expression, up to the next EOI or the closing brace
+    // of a block opened along the way. Unknown tokens skipped are still reported.
+    fn synchronize(&mut self) {
+        // the error was found on the token ending the expression
+        if matches!(self.last, Some(TK![EOI] | TK![RBrace])) {
+            return;
         }
+        let mut depth = 0usize;
+        loop {
+            match self.peek() {
+                TK![EOF] => return,
+                TK![EOI] if depth == 0 => {
+                    self.next();
+                    return;
+                }
+                TK![RBrace] if depth <= 1 => {
+                    self.next();
+                    return;
+                }
+                TK![LBrace] => depth += 1,
+                TK![RBrace] => depth -= 1,
+                TK![Error] => {
+                    // peek guarantees there is a next token
+                    let token = *self.tokens.peek().unwrap();
+                    self.diagnostics.push(unknown_token(token, self.input));
+                }
+                _ => (),
+            }
+            self.next();
+        }
+    }
+
+    // Parses the content of a block up to its closing brace, the tokens of the `invalid`
+    // kinds are reported and skipped. Nested blocks are parsed by the inner parser.
+    fn parse_block(
+        &mut self,
+        lbrace: Token,
+        name: &str,
+        code: &'static str,
+        invalid: &[TokenKind],
+    ) -> Result<Vec<Expression>, Diagnostic> {
+        let unclosed = || {
+            Diagnostic::error(
+                codes::UNCLOSED_BLOCK,
+                "Missing '}' character, '{' was never closed",
+                lbrace.span,
+            )
+        };
+
+        let mut valid_tokens: Vec<Token> = Vec::new();
+        // braces of nested blocks
+        let mut depth = 0usize;
+        loop {
+            let token = self.next().ok_or_else(unclosed)?;
+            match token.kind {
+                TK![RBrace] if depth == 0 => {
+                    // the closing brace also ends the last expression, e.g. `{ Tap a }`
+                    valid_tokens.push(Token {
+                        kind: TK![EOI],
+                        span: token.span.empty(),
+                    });
+                    break;
+                }
+                TK![RBrace] => depth -= 1,
+                TK![LBrace] => depth += 1,
+                // the lexer always ends with an EOF token
+                TK![EOF] => Err(unclosed())?,
+                kind if invalid.contains(&kind) => {
+                    self.diagnostics.push(Diagnostic::error(
+                        code,
+                        format!("Invalid token '{}' inside {}", kind, name),
+                        token.span,
+                    ));
+                    continue;
+                }
+                _ => (),
+            }
+            valid_tokens.push(token);
+        }
+
+        let span = tracing::span!(tracing::Level::TRACE, "Block Parsing", name);
+        let _guard = span.enter();
+        // variables declared in the block are only visible inside it, as it may not run
+        let outer_variables = self.variables.clone();
+        let mut inner = self.child(self.input, self.file, valid_tokens.into_iter());
+        let expressions = inner.parse_all();
+        self.absorb(inner);
+        self.variables = outer_variables;
         Ok(expressions)
     }
 
-    fn parse_expression(&mut self) -> anyhow::Result<Option<Expression>> {
+    // Returns `None` when nothing has to be executed, e.g. an empty line or a function
+    fn parse_expression(&mut self) -> Result<Option<Expression>, Diagnostic> {
         match self.peek() {
             TK![def] => {
                 self.consume(TK![def])?;
                 let name_token = self.consume(TK![Word])?;
                 let name = self.text(name_token).to_uppercase();
                 self.consume(TK![=])?;
-                match name.as_str() {
+                let expression = match name.as_str() {
                     "RESOLUTION" => {
-                        let resolution =
-                            token_to_position(self.consume(TK![Position])?, self.input)?;
-                        self.consume(TK![EOI])?;
-                        Ok(Some(Expression::Resolution(resolution)))
+                        let width = token_to_int(self.consume(TK![Float])?, self.input)?;
+                        self.consume(TK![,])?;
+                        let height = token_to_int(self.consume(TK![Float])?, self.input)?;
+                        Expression::Resolution((width, height))
                     }
                     "DELAY_BETWEEN_ACTIONS" => {
                         let milliseconds =
                             token_to_float(self.consume(TK![Float])?, self.input)? as u64;
-                        self.consume(TK![EOI])?;
-                        Ok(Some(Expression::DelayBetweenActions(milliseconds)))
-                    }
-                    "GLOBAL_HALT_KEY" => {
-                        let button = token_to_button(self.consume(TK![Word])?, self.input)?;
-                        self.consume(TK![EOI])?;
-                        Ok(Some(Expression::GlobalHaltKey(button)))
+                        Expression::DelayBetweenActions(milliseconds)
                     }
-                    _ => {
-                        tracing::error!("Failed to assign the unknown definition '{}'", &name);
-                        Err(anyhow::anyhow!("Unkown definition"))
+                    "GLOBAL_HALT_KEY" => Expression::GlobalHaltKey(self.parse_hotkey()?),
+                    "GLOBAL_PAUSE_KEY" => Expression::GlobalPauseKey(self.parse_hotkey()?),
+                    "KEYBOARD_LAYOUT" => {
+                        let name_token = self.consume(TK![String])?;
+                        let name = token_to_string(name_token, self.input)?;
+                        let layout = KeyboardLayout::by_name(&name).ok_or_else(|| {
+                            let names: Vec<&str> =
+                                KeyboardLayout::all().iter().map(|l| l.name).collect();
+                            Diagnostic::error(
+                                codes::UNKNOWN_LAYOUT,
+                                format!("Unknown keyboard layout '{}'", name),
+                                name_token.span,
+                            )
+                            .with_help(format!("expected one of {}", names.join(", ")))
+                        })?;
+                        Expression::KeyboardLayout(layout)
                     }
-                }
+                    _ => Err(Diagnostic::error(
+                        codes::UNKNOWN_DEFINITION,
+                        format!("Unknown definition '{}'", self.text(name_token)),
+                        name_token.span,
+                    )
+                    .with_help(
+                        "expected one of RESOLUTION, DELAY_BETWEEN_ACTIONS, GLOBAL_HALT_KEY, \
+                        GLOBAL_PAUSE_KEY or KEYBOARD_LAYOUT",
+                    ))?,
+                };
+                self.consume(TK![EOI])?;
+                self.define(name, expression, name_token.span)
             }
             TK![Move] => {
                 self.consume(TK![Move])?;
-                let position = token_to_position(self.consume(TK![Position])?, self.input)?;
+                let x = self.parse_term()?;
+                self.consume(TK![,])?;
+                let y = self.parse_term()?;
                 self.consume(TK![EOI])?;
-                Ok(Some(Expression::Move(position)))
+                Ok(Some(Expression::Move(x, y)))
             }
             TK![Tap] => {
                 self.consume(TK![Tap])?;
-                let button = token_to_button(self.consume(TK![Word])?, self.input)?;
+                let chord = self.parse_chord()?;
                 self.consume(TK![EOI])?;
-                Ok(Some(Expression::Tap(button)))
+                Ok(Some(Expression::Tap(chord)))
             }
             TK![Press] => {
                 self.consume(TK![Press])?;
-                let button = token_to_button(self.consume(TK![Word])?, self.input)?;
+                let chord = self.parse_chord()?;
                 self.consume(TK![EOI])?;
-                Ok(Some(Expression::Press(button)))
+                Ok(Some(Expression::Press(chord)))
             }
             TK![Release] => {
                 self.consume(TK![Release])?;
-                let button = token_to_button(self.consume(TK![Word])?, self.input)?;
+                let chord = self.parse_chord()?;
                 self.consume(TK![EOI])?;
-                Ok(Some(Expression::Release(button)))
+                Ok(Some(Expression::Release(chord)))
             }
             TK![Sleep] => {
                 self.consume(TK![Sleep])?;
-                let time = token_to_float(self.consume(TK![Float])?, self.input).unwrap();
+                let seconds = self.parse_term()?;
                 self.consume(TK![EOI])?;
-                Ok(Some(Expression::Sleep(time)))
+                Ok(Some(Expression::Sleep(seconds)))
             }
             TK![Type] => {
                 self.consume(TK![Type])?;
-                let string = token_to_string(self.consume(TK![String])?, self.input).unwrap();
+                let text = self.parse_term()?;
                 self.consume(TK![EOI])?;
-                Ok(Some(Expression::Type(string)))
+                Ok(Some(Expression::Type(text)))
             }
             TK![Await] => {
                 self.consume(TK![Await])?;
-                let token = self.next().ok_or(anyhow::anyhow!(
-                    "Expected either an EOI or Button token after Await, found None"
-                ))?;
+                if self.peek() == TK![Word] {
+                    let hotkey = self.parse_hotkey()?;
+                    self.consume(TK![EOI])?;
+                    return Ok(Some(Expression::AwaitKey(hotkey)));
+                }
+                let token = self.next().ok_or_else(|| {
+                    Diagnostic::error(
+                        codes::UNEXPECTED_EOF,
+                        "Expected either an EOI or a button after Await, but the file ended",
+                        self.eof_span(),
+                    )
+                })?;
                 match token.kind {
                     TK![EOI] => Ok(Some(Expression::Await)),
-                    TK![Word] => Ok(Some(Expression::AwaitKey(token_to_button(
-                        token, self.input,
-                    )?))),
-                    other => Err(anyhow::anyhow!(
-                        "Expected either an EOI or Button token after Await, found '{}'",
-                        other
+                    _ => Err(Diagnostic::error(
+                        codes::UNEXPECTED_TOKEN,
+                        format!(
+                            "Expected either an EOI or a button after Await, found '{}'",
+                            token.kind
+                        ),
+                        token.span,
                     )),
                 }
             }
             TK![Bind] => {
                 self.consume(TK![Bind])?;
-                let button = token_to_button(self.consume(TK![Word])?, self.input)?;
-                self.consume(TK![LBrace])?;
-
-                let mut valid_tokens: Vec<Token> = Vec::new();
-                loop {
-                    let token = self.next().ok_or_else(|| {
-                        tracing::error!("Missing '}}' character, '{{' was never closed");
-                        anyhow::anyhow!("Parsing failed")
-                    })?;
-
-                    match token.kind {
-                        TK![RBrace] => break,
-                        invalid @ (TK![LBrace] | TK![Await] | TK![def] | TK![Bind]) => {
-                            tracing::error!("Invalid token '{}' inside bind", invalid);
-                            Err(anyhow::anyhow!("Parsing failed"))?;
-                        }
-                        _ => valid_tokens.push(token),
-                    }
-                }
+                let hotkey = self.parse_hotkey()?;
+                let lbrace = self.consume(TK![LBrace])?;
+
+                self.in_bind = true;
+                let inner_expressions = self.parse_block(
+                    lbrace,
+                    "bind",
+                    codes::INVALID_IN_BIND,
+                    &[TK![Await], TK![def], TK![Bind], TK![Function], TK![Include]],
+                );
+                self.in_bind = false;
+                let inner_expressions = inner_expressions?;
 
-                let span = tracing::span!(tracing::Level::TRACE, "Bind Parsing");
-                let _guard = span.enter();
-                let inner_expressions: Vec<Expression> = Parser {
-                    input: self.input,
-                    tokens: valid_tokens.into_iter().peekable(),
+                Ok(Some(Expression::Bind(hotkey, inner_expressions)))
+            }
+            TK![Repeat] => {
+                self.consume(TK![Repeat])?;
+                let (count, forever) = match self.tokens.peek().copied() {
+                    Some(token) if token.kind == TK![Word] && self.text(token) == "forever" => {
+                        self.next();
+                        (None, Some(token))
+                    }
+                    _ => (Some(self.parse_count()?), None),
+                };
+                let lbrace = self.consume(TK![LBrace])?;
+                let inner_expressions = self.parse_block(
+                    lbrace,
+                    "repeat",
+                    codes::INVALID_IN_REPEAT,
+                    &[TK![def], TK![Bind], TK![Function], TK![Include]],
+                )?;
+                if let Some(forever) = forever.filter(|_| inner_expressions.is_empty()) {
+                    Err(Diagnostic::error(
+                        codes::EMPTY_REPEAT,
+                        "Repeat forever with an empty body",
+                        forever.span,
+                    )
+                    .with_help("it would only wait for the global halt key, use Await instead"))?
                 }
-                .process()?;
-                drop(_guard);
 
-                Ok(Some(Expression::Bind(button, inner_expressions)))
+                Ok(Some(Expression::Repeat(count, inner_expressions)))
+            }
+            TK![If] => {
+                self.consume(TK![If])?;
+                self.parse_if().map(Some)
+            }
+            TK![Function] => {
+                self.consume(TK![Function])?;
+                self.parse_function()?;
+                Ok(None)
+            }
+            TK![Call] => {
+                self.consume(TK![Call])?;
+                self.parse_call().map(Some)
             }
             TK![Print] => {
                 self.consume(TK![Print])?;
-                let string = token_to_string(self.consume(TK![String])?, self.input)?;
+                let text = self.parse_term()?;
                 self.consume(TK![EOI])?;
-                Ok(Some(Expression::Print(string)))
+                Ok(Some(Expression::Print(text)))
             }
             TK![Println] => {
                 self.consume(TK![Println])?;
-                let mut string = token_to_string(self.consume(TK![String])?, self.input)?;
-                string.push('\n');
+                let text = self.parse_term()?;
                 self.consume(TK![EOI])?;
-                Ok(Some(Expression::Print(string)))
+                Ok(Some(Expression::Print(Term::Binary(
+                    Box::new(text),
+                    Operator::Add,
+                    Box::new(Term::string("\n")),
+                ))))
             }
             TK![PrintClipboard] => {
                 self.consume(TK![PrintClipboard])?;
@@ -204,37 +621,119 @@ where
             }
             TK![ScrollUp] => {
                 self.consume(TK![ScrollUp])?;
-                let amount = token_to_float(self.consume(TK![Float])?, self.input)? as i32;
+                let amount = self.parse_term()?;
                 self.consume(TK![EOI])?;
                 Ok(Some(Expression::Scroll(amount)))
             }
             TK![ScrollDown] => {
                 self.consume(TK![ScrollDown])?;
-                let amount = token_to_float(self.consume(TK![Float])?, self.input)? as i32;
+                let amount = self.parse_term()?;
+                self.consume(TK![EOI])?;
+                Ok(Some(Expression::Scroll(Term::Negate(Box::new(amount)))))
+            }
+            TK![let] => {
+                self.consume(TK![let])?;
+                let name_token = self.next().ok_or_else(|| {
+                    Diagnostic::error(
+                        codes::UNEXPECTED_EOF,
+                        "Expected a variable name after let, but the file ended",
+                        self.eof_span(),
+                    )
+                })?;
+                let name = match name_token.kind {
+                    TK![Word] => self.text(name_token).to_string(),
+                    TK![Variable] => Err(Diagnostic::error(
+                        codes::UNEXPECTED_TOKEN,
+                        format!(
+                            "Unexpected '$' in the declaration of '{}'",
+                            self.text(name_token)
+                        ),
+                        name_token.span,
+                    )
+                    .with_help(format!(
+                        "variables are declared without '$', e.g. 'let {} = 1'",
+                        &self.text(name_token)[1..]
+                    )))?,
+                    _ => Err(unexpected(name_token, TK![Word], self.input))?,
+                };
+                if self.parameters.contains(&name) {
+                    Err(Diagnostic::error(
+                        codes::INVALID_IN_FUNCTION,
+                        format!("Cannot assign to the parameter '{}'", name),
+                        name_token.span,
+                    )
+                    .with_help("parameters are read-only, declare a variable with another name"))?
+                }
+                self.consume(TK![=])?;
+                let value = self.parse_term()?;
                 self.consume(TK![EOI])?;
-                Ok(Some(Expression::Scroll(-amount)))
+                // declared after its value so that `let x = $x` is rejected
+                self.variables.insert(name.clone());
+                Ok(Some(Expression::Let(name, value)))
             }
             TK![EOI] => {
                 self.consume(TK![EOI])?;
-                self.parse_expression()
+                Ok(None)
             }
             TK![EOF] => Ok(None),
-            undefined => {
-                tracing::error!("Undefined parsing behavior for TokenKind '{:?}'", undefined);
-                Err(anyhow::anyhow!("Parsing failed"))
+            _ => {
+                // peek guarantees there is a next token
+                let token = self.next().unwrap();
+                Err(expected_expression(token, self.input))
             }
         }
     }
 }
 
+fn unexpected(token: Token, expected: TokenKind, input: &str) -> Diagnostic {
+    match token.kind {
+        TK![Error] => unknown_token(token, input),
+        TK![EOF] => Diagnostic::error(
+            codes::UNEXPECTED_EOF,
+            format!("Expected '{}', but the file ended", expected),
+            token.span,
+        ),
+        found => Diagnostic::error(
+            codes::UNEXPECTED_TOKEN,
+            format!("Expected '{}', found '{}'", expected, found),
+            token.span,
+        ),
+    }
+}
+
+fn expected_expression(token: Token, input: &str) -> Diagnostic {
+    match token.kind {
+        TK![Error] => unknown_token(token, input),
+        TK![Word] => Diagnostic::error(
+            codes::UNEXPECTED_TOKEN,
+            format!("Unknown expression '{}'", token.text(input)),
+            token.span,
+        )
+        .with_help("expressions are case sensitive and start with a capital letter, e.g. 'Sleep'"),
+        found => Diagnostic::error(
+            codes::UNEXPECTED_TOKEN,
+            format!("Expected an expression, found '{}'", found),
+            token.span,
+        ),
+    }
+}
+
+fn unknown_token(token: Token, input: &str) -> Diagnostic {
+    Diagnostic::error(
+        codes::UNKNOWN_TOKEN,
+        format!("Unknown token '{}'", token.text(input)),
+        token.span,
+    )
+}
+
 pub struct TokenIter<'input> {
     lexer: Lexer<'input>,
 }
 
 impl<'input> TokenIter<'input> {
-    fn new(input: &'input str) -> Self {
+    fn new(input: &'input str, file: FileId) -> Self {
         Self {
-            lexer: Lexer::new(input),
+            lexer: Lexer::new(input, file),
         }
     }
 }
@@ -254,36 +753,514 @@ impl<'input> Iterator for TokenIter<'input> {
 
 #[cfg(test)]
 mod tests {
-    use super::Parser;
-    use crate::{
-        compiler::{button::Button, expression::Expression},
-        keymap, mousemap,
+    use super::{CompileContext, Modifiers, Parser, codes};
+    use crate::compiler::{
+        button::{Button, Chord, Hotkey},
+        condition::{Comparison, Condition},
+        expression::Expression,
+        value::{Operator, Term},
     };
 
     #[test]
     fn long() {
-        keymap::KeyMap::test_init();
-        mousemap::MouseMap::test_init();
         let input: &str = "define RESOLUTION = 1920, 1080\nBind NR1 {\n  Move 1070, 234\n  Tap LMB\n}\n Press LMB; Sleep 0.1; Release LMB\nTap Space\nType \"Hello World\"";
-        let mut parser = Parser::new(input);
+        let context = CompileContext::default();
+        let mut parser = Parser::new(input, &context);
         let expressions = parser.process().unwrap();
         assert_eq!(
             vec![
                 Expression::Resolution((1920, 1080)),
                 Expression::Bind(
-                    Button::K(inputbot::KeybdKey::Numrow1Key),
+                    Button::K(inputbot::KeybdKey::Numrow1Key).into(),
                     vec![
-                        Expression::Move((1070, 234)),
-                        Expression::Tap(Button::M(inputbot::MouseButton::LeftButton))
+                        Expression::Move(Term::int(1070), Term::int(234)),
+                        Expression::Tap(Button::M(inputbot::MouseButton::LeftButton).into())
                     ]
                 ),
-                Expression::Press(Button::M(inputbot::MouseButton::LeftButton)),
-                Expression::Sleep(0.1),
-                Expression::Release(Button::M(inputbot::MouseButton::LeftButton)),
-                Expression::Tap(Button::K(inputbot::KeybdKey::SpaceKey)),
-                Expression::Type("Hello World".to_string())
+                Expression::Press(Button::M(inputbot::MouseButton::LeftButton).into()),
+                Expression::Sleep(Term::float(0.1)),
+                Expression::Release(Button::M(inputbot::MouseButton::LeftButton).into()),
+                Expression::Tap(Button::K(inputbot::KeybdKey::SpaceKey).into()),
+                Expression::Type(Term::string("Hello World"))
             ],
             expressions
         )
     }
+
+    #[test]
+    fn located_error() {
+        let input: &str = "Sleep 1\nSleep hello";
+        let context = CompileContext::default();
+        let diagnostics = Parser::new(input, &context).process().unwrap_err();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, codes::UNEXPECTED_TOKEN);
+        assert_eq!(
+            &input[diagnostics[0].span.start..diagnostics[0].span.end],
+            "hello"
+        );
+    }
+
+    #[test]
+    fn reports_every_error() {
+        let input: &str = "Sleep hello\nSleep\nSleep 1 ~ 2\nFoo\nType \"ok\"\nPrintClipboard 3";
+        let context = CompileContext::default();
+        let diagnostics = Parser::new(input, &context).process().unwrap_err();
+        let found: Vec<(&str, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.code,
+                    &input[diagnostic.span.start..diagnostic.span.end],
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (codes::UNEXPECTED_TOKEN, "hello"),
+                (codes::UNEXPECTED_TOKEN, "\n"),
+                (codes::UNKNOWN_TOKEN, "~"),
+                (codes::UNEXPECTED_TOKEN, "Foo"),
+                (codes::UNEXPECTED_TOKEN, "3"),
+            ],
+            found
+        );
+    }
+
+    #[test]
+    fn repeat_blocks() {
+        let input: &str = "Repeat 3 {\n  Sleep 0.1\n  Repeat forever { Type \"a\" }\n}\nRepeat 2.5 {}\nRepeat 2 {}\nRepeat forever {\n  // nothing\n}";
+        let context = CompileContext::default();
+        let mut parser = Parser::new(input, &context);
+        let expressions = parser.parse_all();
+        assert_eq!(
+            vec![
+                Expression::Repeat(
+                    Some(Term::int(3)),
+                    vec![
+                        Expression::Sleep(Term::float(0.1)),
+                        Expression::Repeat(None, vec![Expression::Type(Term::string("a"))])
+                    ]
+                ),
+                Expression::Repeat(Some(Term::int(2)), vec![])
+            ],
+            expressions
+        );
+        assert_eq!(parser.diagnostics.len(), 2);
+        assert_eq!(parser.diagnostics[0].code, codes::INVALID_NUMBER);
+        assert_eq!(parser.diagnostics[1].code, codes::EMPTY_REPEAT);
+        assert_eq!(&input[parser.diagnostics[1].span], "forever");
+    }
+
+    #[test]
+    fn block_scoped_variables() {
+        let input: &str = "let x = 1\nIf pressed a {\n  let x = $x + 1\n  let y = $x\n}\nRepeat 2 { let z = 1 }\nMove $x, $y\nType $z";
+        let context = CompileContext::default();
+        let mut parser = Parser::new(input, &context);
+        parser.parse_all();
+        let spans: Vec<&str> = parser
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                assert_eq!(diagnostic.code, codes::UNDEFINED_VARIABLE);
+                &input[diagnostic.span]
+            })
+            .collect();
+        assert_eq!(spans, vec!["$y", "$z"]);
+    }
+
+    #[test]
+    fn variables_and_arithmetic() {
+        let input: &str =
+            "let x = 100\nlet step = ($x - 20) / 2\nMove $x + $step * 2, -$step\nType \"x=\" + $x";
+        let context = CompileContext::default();
+        let expressions = Parser::new(input, &context).process().unwrap();
+        let var = |name: &str| Box::new(Term::Variable(name.to_string()));
+        assert_eq!(
+            vec![
+                Expression::Let("x".to_string(), Term::int(100)),
+                Expression::Let(
+                    "step".to_string(),
+                    Term::Binary(
+                        Box::new(Term::Binary(
+                            var("x"),
+                            Operator::Sub,
+                            Box::new(Term::int(20))
+                        )),
+                        Operator::Div,
+                        Box::new(Term::int(2))
+                    )
+                ),
+                Expression::Move(
+                    Term::Binary(
+                        var("x"),
+                        Operator::Add,
+                        Box::new(Term::Binary(
+                            var("step"),
+                            Operator::Mul,
+                            Box::new(Term::int(2))
+                        ))
+                    ),
+                    Term::Negate(var("step"))
+                ),
+                Expression::Type(Term::Binary(
+                    Box::new(Term::string("x=")),
+                    Operator::Add,
+                    var("x")
+                )),
+            ],
+            expressions
+        );
+    }
+
+    #[test]
+    fn invalid_terms() {
+        let input: &str = "Sleep $delay\nlet y = $y\nType \"a\" * 2\nScrollDown 1 / 0";
+        let context = CompileContext::default();
+        let diagnostics = Parser::new(input, &context).process().unwrap_err();
+        let codes: Vec<&str> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect();
+        assert_eq!(
+            vec![
+                codes::UNDEFINED_VARIABLE,
+                codes::UNDEFINED_VARIABLE,
+                codes::INVALID_OPERATION,
+                codes::INVALID_OPERATION
+            ],
+            codes
+        );
+    }
+
+    #[test]
+    fn if_else_chain() {
+        let input: &str = "let x = 2\nIf pressed lmb { Tap a } Else If clipboard matches \"^\\\\d+$\" {\n  Tap b\n}\nElse {\n  If $x >= 2 { Tap c }\n}\nIf toggled lmb {}\nIf clipboard = \"a\" {}";
+        let context = CompileContext::default();
+        let mut parser = Parser::new(input, &context);
+        let expressions = parser.parse_all();
+        let tap = |key| Expression::Tap(Button::K(key).into());
+        let Expression::If(_, _, otherwise) = &expressions[1] else {
+            panic!("Expected an if, found {:?}", expressions[1]);
+        };
+        let Expression::If(Condition::ClipboardMatches(pattern), ..) = &otherwise[0] else {
+            panic!("Expected an else if, found {:?}", otherwise[0]);
+        };
+        assert_eq!(pattern.0.as_str(), "^\\d+$");
+        assert_eq!(
+            Expression::If(
+                Condition::Pressed(Button::M(inputbot::MouseButton::LeftButton)),
+                vec![tap(inputbot::KeybdKey::AKey)],
+                vec![Expression::If(
+                    Condition::ClipboardMatches(pattern.clone()),
+                    vec![tap(inputbot::KeybdKey::BKey)],
+                    vec![Expression::If(
+                        Condition::Compare(
+                            Term::Variable("x".to_string()),
+                            Comparison::GtEq,
+                            Term::int(2)
+                        ),
+                        vec![tap(inputbot::KeybdKey::CKey)],
+                        vec![]
+                    )]
+                )]
+            ),
+            expressions[1]
+        );
+        let codes: Vec<&str> = parser
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect();
+        assert_eq!(
+            vec![codes::INVALID_CONDITION, codes::UNEXPECTED_TOKEN],
+            codes
+        );
+    }
+
+    #[test]
+    fn awaiting_function_in_bind() {
+        let input: &str = "Function wait() {\n  Repeat 2 { Await }\n}\nFunction outer() { Call wait() }\nFunction tap() { Tap a }\nBind nr1 { Call tap() }\nBind nr2 { Call outer() }\nCall wait()";
+        let context = CompileContext::default();
+        let mut parser = Parser::new(input, &context);
+        let expressions = parser.parse_all();
+        assert_eq!(expressions.len(), 3);
+        let found: Vec<(&str, &str)> = parser
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.code,
+                    &input[diagnostic.span.start..diagnostic.span.end],
+                )
+            })
+            .collect();
+        assert_eq!(vec![(codes::INVALID_IN_BIND, "outer")], found);
+    }
+
+    #[test]
+    fn functions() {
+        let input: &str = "Function fill(x, text) {\n  Move $x, 10\n  Type $text\n}\nCall fill(5, \"a\")\nCall fill(1)\nCall missing()\nFunction fill() {}\nFunction set(a) { let a = 1 }\nMove $x, 1";
+        let context = CompileContext::default();
+        let mut parser = Parser::new(input, &context);
+        let expressions = parser.parse_all();
+        let Expression::Call(function, arguments) = &expressions[0] else {
+            panic!("Expected a call, found {:?}", expressions[0]);
+        };
+        assert_eq!(function.name, "fill");
+        assert_eq!(function.parameters, vec!["x", "text"]);
+        assert_eq!(
+            function.body,
+            vec![
+                Expression::Move(Term::Variable("x".to_string()), Term::int(10)),
+                Expression::Type(Term::Variable("text".to_string())),
+            ]
+        );
+        assert_eq!(arguments, &vec![Term::int(5), Term::string("a")]);
+        let codes: Vec<&str> = parser
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect();
+        assert_eq!(
+            vec![
+                codes::ARGUMENT_COUNT,
+                codes::UNDEFINED_FUNCTION,
+                codes::DUPLICATE_DEFINITION,
+                codes::INVALID_IN_FUNCTION,
+                // parameters are not visible outside of the function
+                codes::UNDEFINED_VARIABLE,
+            ],
+            codes
+        );
+    }
+
+    #[test]
+    fn includes() {
+        let root = std::env::temp_dir().join(format!("ba-includes-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("common")).unwrap();
+        std::fs::write(
+            root.join("common/login.ba"),
+            "define DELAY_BETWEEN_ACTIONS = 10\nlet user = \"me\"\nType $user",
+        )
+        .unwrap();
+        std::fs::write(root.join("common/loop.ba"), "Include \"common/loop.ba\"").unwrap();
+        let outside = std::env::temp_dir().join(format!("ba-outside-{}.ba", std::process::id()));
+        std::fs::write(&outside, "Tap a").unwrap();
+        // written with forward slashes, which every platform accepts
+        let outside_path = outside.display().to_string().replace('\\', "/");
+        std::fs::write(
+            root.join("common/conflict.ba"),
+            "define DELAY_BETWEEN_ACTIONS = 20\nSleep oops",
+        )
+        .unwrap();
+
+        let input: &str = "define DELAY_BETWEEN_ACTIONS = 10\nInclude \"common/login.ba\"\nPrint $user\nInclude \"common/loop.ba\"\nInclude \"common/conflict.ba\"\nInclude \"missing.ba\"";
+        let input = format!(
+            "{}\nInclude \"../ba-outside-{}.ba\"\nInclude \"{}\"",
+            input,
+            std::process::id(),
+            outside_path
+        );
+        let context = CompileContext::default();
+        let mut parser = Parser::new(&input, &context).with_root(&root);
+        let expressions = parser.parse_all();
+        std::fs::remove_dir_all(&root).unwrap();
+        std::fs::remove_file(&outside).unwrap();
+
+        // the repeated definition is merged
+        assert_eq!(
+            vec![
+                Expression::DelayBetweenActions(10),
+                Expression::Let("user".to_string(), Term::string("me")),
+                Expression::Type(Term::Variable("user".to_string())),
+                Expression::Print(Term::Variable("user".to_string())),
+            ],
+            expressions
+        );
+        let outside_relative = format!("\"../ba-outside-{}.ba\"", std::process::id());
+        let outside_absolute = format!("\"{}\"", outside_path);
+        let found: Vec<(&str, &str, &str)> = parser
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let source = parser.sources().get(diagnostic.span.file);
+                (
+                    diagnostic.code,
+                    source.name.as_str(),
+                    &source.content[diagnostic.span.start..diagnostic.span.end],
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (codes::INCLUDE_CYCLE, "common/loop.ba", "\"common/loop.ba\""),
+                (
+                    codes::CONFLICTING_DEFINITION,
+                    "common/conflict.ba",
+                    "DELAY_BETWEEN_ACTIONS"
+                ),
+                (codes::UNEXPECTED_TOKEN, "common/conflict.ba", "oops"),
+                (codes::INCLUDE_FAILED, "main.ba", "\"missing.ba\""),
+                (codes::INCLUDE_OUTSIDE, "main.ba", outside_relative.as_str()),
+                (codes::INCLUDE_OUTSIDE, "main.ba", outside_absolute.as_str()),
+            ],
+            found
+        );
+    }
+
+    #[test]
+    fn diamond_includes() {
+        let root = std::env::temp_dir().join(format!("ba-diamond-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("common.ba"), "Function greet() { Type \"hi\" }").unwrap();
+        std::fs::write(root.join("a.ba"), "Include \"common.ba\"\nCall greet()").unwrap();
+        std::fs::write(root.join("b.ba"), "Include \"./common.ba\"\nCall greet()").unwrap();
+
+        let input: &str = "Include \"a.ba\"\nInclude \"b.ba\"";
+        let context = CompileContext::default();
+        let result = Parser::new(input, &context).with_root(&root).process();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        // common.ba is parsed once, its function is not defined twice
+        let expressions = result.unwrap();
+        assert_eq!(expressions.len(), 2);
+        assert!(
+            expressions.iter().all(
+                |expr| matches!(expr, Expression::Call(function, _) if function.name == "greet")
+            )
+        );
+    }
+
+    #[test]
+    fn chords() {
+        let input: &str = "Tap ctrl+shift+s\nPress ctrl + lmb\nRelease ctrl+ctrl";
+        let context = CompileContext::default();
+        let mut parser = Parser::new(input, &context);
+        let expressions = parser.parse_all();
+        assert_eq!(
+            vec![
+                Expression::Tap(Chord(vec![
+                    Button::K(inputbot::KeybdKey::LControlKey),
+                    Button::K(inputbot::KeybdKey::LShiftKey),
+                    Button::K(inputbot::KeybdKey::SKey),
+                ])),
+                Expression::Press(Chord(vec![
+                    Button::K(inputbot::KeybdKey::LControlKey),
+                    Button::M(inputbot::MouseButton::LeftButton),
+                ])),
+            ],
+            expressions
+        );
+        assert_eq!(parser.diagnostics.len(), 1);
+        assert_eq!(parser.diagnostics[0].code, codes::DUPLICATE_BUTTON);
+        assert_eq!(&input[parser.diagnostics[0].span], "ctrl");
+        assert_eq!(parser.diagnostics[0].span.start, input.len() - 4);
+    }
+
+    #[test]
+    fn hotkeys() {
+        let input: &str = "define GLOBAL_HALT_KEY = ctrl+Esc\nBind lctrl+alt+nr1 { Tap a }\nAwait shift+F2\nBind a+b {}";
+        let context = CompileContext::default();
+        let mut parser = Parser::new(input, &context);
+        let expressions = parser.parse_all();
+        let modifiers = |keys: &[inputbot::KeybdKey]| {
+            keys.iter()
+                .map(|key| Modifiers::from_key(*key).unwrap())
+                .fold(Modifiers::default(), Modifiers::union)
+        };
+        assert_eq!(
+            vec![
+                Expression::GlobalHaltKey(Hotkey {
+                    modifiers: modifiers(&[inputbot::KeybdKey::LControlKey]),
+                    button: Button::K(inputbot::KeybdKey::EscapeKey),
+                    passthrough: false,
+                }),
+                Expression::Bind(
+                    Hotkey {
+                        modifiers: modifiers(&[
+                            inputbot::KeybdKey::LControlKey,
+                            inputbot::KeybdKey::LAltKey
+                        ]),
+                        button: Button::K(inputbot::KeybdKey::Numrow1Key),
+                        passthrough: false,
+                    },
+                    vec![Expression::Tap(Button::K(inputbot::KeybdKey::AKey).into())]
+                ),
+                Expression::AwaitKey(Hotkey {
+                    modifiers: modifiers(&[inputbot::KeybdKey::LShiftKey]),
+                    button: Button::K(inputbot::KeybdKey::F2Key),
+                    passthrough: false,
+                }),
+            ],
+            expressions
+        );
+        assert_eq!(parser.diagnostics.len(), 1);
+        assert_eq!(parser.diagnostics[0].code, codes::INVALID_HOTKEY);
+        assert_eq!(&input[parser.diagnostics[0].span], "a");
+    }
+
+    #[test]
+    fn mouse_hotkeys() {
+        let input: &str =
+            "Bind x1 {}\nAwait mmb passthrough\nBind ctrl+x2 {}\nBind nr1 passthrough {}";
+        let context = CompileContext::default();
+        let mut parser = Parser::new(input, &context);
+        let expressions = parser.parse_all();
+        assert_eq!(
+            vec![
+                Expression::Bind(Button::M(inputbot::MouseButton::X1Button).into(), vec![]),
+                Expression::AwaitKey(Hotkey {
+                    modifiers: Modifiers::default(),
+                    button: Button::M(inputbot::MouseButton::MiddleButton),
+                    passthrough: true,
+                }),
+            ],
+            expressions
+        );
+        let spans: Vec<&str> = parser
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                assert_eq!(diagnostic.code, codes::INVALID_HOTKEY);
+                &input[diagnostic.span]
+            })
+            .collect();
+        assert_eq!(spans, vec!["ctrl+x2", "nr1 passthrough"]);
+    }
+
+    #[test]
+    fn keyboard_layouts() {
+        let input: &str = "define KEYBOARD_LAYOUT = \"de\"\ndefine KEYBOARD_LAYOUT = \"dvorak\"";
+        let context = CompileContext::default();
+        let mut parser = Parser::new(input, &context);
+        let expressions = parser.parse_all();
+        assert_eq!(
+            vec![Expression::KeyboardLayout(
+                inputbot::KeyboardLayout::by_name("de").unwrap()
+            )],
+            expressions
+        );
+        assert_eq!(parser.diagnostics.len(), 1);
+        assert_eq!(parser.diagnostics[0].code, codes::UNKNOWN_LAYOUT);
+        assert_eq!(&input[parser.diagnostics[0].span], "\"dvorak\"");
+    }
+
+    #[test]
+    fn layout_names() {
+        let input: &str =
+            "// names of the German layout\ndefine keyboard_layout = \"de\"\nTap komma";
+        let layout = Parser::keyboard_layout_of(input).unwrap();
+        assert_eq!(layout.name, "de");
+        assert!(Parser::keyboard_layout_of("define KEYBOARD_LAYOUT = \"dvorak\"").is_none());
+        assert!(Parser::keyboard_layout_of("Tap comma").is_none());
+
+        let context = CompileContext::default().with_keyboard_layout(layout);
+        let mut parser = Parser::new(input, &context);
+        let expressions = parser.parse_all();
+        assert!(parser.diagnostics.is_empty());
+        assert_eq!(
+            expressions[1],
+            Expression::Tap(Button::K(inputbot::KeybdKey::CommaKey).into())
+        );
+    }
 }
@@ -0,0 +1,162 @@
+// Grammar of the arithmetic terms, from the lowest to the highest precedence:
+// sum     := product (('+' | '-') product)*
+// product := unary (('*' | '/') unary)*
+// unary   := '-' unary | primary
+// primary := number | string | variable | '(' sum ')'
+
+use super::{
+    Parser,
+    ast::{token_to_number, token_to_string},
+    unknown_token,
+};
+use crate::{
+    TK,
+    compiler::{
+        Span, Token,
+        diagnostic::{Diagnostic, codes},
+        value::{Operator, Term, Value},
+    },
+};
+
+impl<'input, I> Parser<'input, I>
+where
+    I: Iterator<Item = Token>,
+{
+    /// Parses a term, terms without variables are evaluated right away to report
+    /// invalid operations at compile time
+    pub(super) fn parse_term(&mut self) -> Result<Term, Diagnostic> {
+        let (term, span) = self.parse_sum()?;
+        if let Some(Err(err)) = term.constant() {
+            Err(Diagnostic::error(
+                codes::INVALID_OPERATION,
+                err.to_string(),
+                span,
+            ))?
+        }
+        Ok(term)
+    }
+
+    /// Parses a term used as a number of iterations
+    pub(super) fn parse_count(&mut self) -> Result<Term, Diagnostic> {
+        let (term, span) = self.parse_sum()?;
+        match term.constant() {
+            Some(Err(err)) => Err(Diagnostic::error(
+                codes::INVALID_OPERATION,
+                err.to_string(),
+                span,
+            ))?,
+            Some(Ok(value)) => {
+                if let Err(err) = value.as_count() {
+                    Err(Diagnostic::error(
+                        codes::INVALID_NUMBER,
+                        format!("Invalid count, {}", err),
+                        span,
+                    ))?
+                }
+            }
+            None => (),
+        }
+        Ok(term)
+    }
+
+    fn parse_sum(&mut self) -> Result<(Term, Span), Diagnostic> {
+        let (mut term, mut span) = self.parse_product()?;
+        loop {
+            let operator = match self.peek() {
+                TK![+] => Operator::Add,
+                TK![-] => Operator::Sub,
+                _ => return Ok((term, span)),
+            };
+            self.next();
+            let (rhs, rhs_span) = self.parse_product()?;
+            term = Term::Binary(Box::new(term), operator, Box::new(rhs));
+            span.end = rhs_span.end;
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<(Term, Span), Diagnostic> {
+        let (mut term, mut span) = self.parse_unary()?;
+        loop {
+            let operator = match self.peek() {
+                TK![*] => Operator::Mul,
+                TK![/] => Operator::Div,
+                _ => return Ok((term, span)),
+            };
+            self.next();
+            let (rhs, rhs_span) = self.parse_unary()?;
+            term = Term::Binary(Box::new(term), operator, Box::new(rhs));
+            span.end = rhs_span.end;
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<(Term, Span), Diagnostic> {
+        if self.peek() == TK![-] {
+            // peek guarantees there is a next token
+            let minus = self.next().unwrap();
+            let (term, span) = self.parse_unary()?;
+            return Ok((Term::Negate(Box::new(term)), minus.span.to(span)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<(Term, Span), Diagnostic> {
+        let token = self.next().ok_or_else(|| {
+            Diagnostic::error(
+                codes::UNEXPECTED_EOF,
+                "Expected a value, but the file ended",
+                self.eof_span(),
+            )
+        })?;
+        match token.kind {
+            TK![Float] => Ok((
+                Term::Literal(token_to_number(token, self.input)?),
+                token.span,
+            )),
+            TK![String] => Ok((
+                Term::Literal(Value::String(token_to_string(token, self.input)?)),
+                token.span,
+            )),
+            TK![Variable] => {
+                // without the leading '$'
+                let name = &self.text(token)[1..];
+                if !self.variables.contains(name) {
+                    Err(Diagnostic::error(
+                        codes::UNDEFINED_VARIABLE,
+                        format!("Variable '${}' is not defined", name),
+                        token.span,
+                    )
+                    .with_help(format!("declare it beforehand, e.g. 'let {} = 1'", name)))?
+                }
+                Ok((Term::Variable(name.to_string()), token.span))
+            }
+            TK![LParen] => {
+                let (term, _) = self.parse_sum()?;
+                let rparen = self.consume(TK![RParen])?;
+                Ok((term, token.span.to(rparen.span)))
+            }
+            _ => Err(expected_value(token, self.input)),
+        }
+    }
+}
+
+fn expected_value(token: Token, input: &str) -> Diagnostic {
+    match token.kind {
+        TK![Error] => unknown_token(token, input),
+        TK![EOF] => Diagnostic::error(
+            codes::UNEXPECTED_EOF,
+            "Expected a value, but the file ended",
+            token.span,
+        ),
+        TK![Word] => Diagnostic::error(
+            codes::UNEXPECTED_TOKEN,
+            format!("Expected a value, found '{}'", token.text(input)),
+            token.span,
+        )
+        .with_help("strings are written between double quotes and variables start with '$'"),
+        found => Diagnostic::error(
+            codes::UNEXPECTED_TOKEN,
+            format!("Expected a value, found '{}'", found),
+            token.span,
+        ),
+    }
+}
@@ -0,0 +1,58 @@
+use super::Span;
+
+/// Identifies a file of a `SourceMap`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FileId(usize);
+
+impl FileId {
+    /// The file given to the parser, always the first one of a `SourceMap`
+    pub const MAIN: FileId = FileId(0);
+}
+
+#[derive(Debug)]
+pub struct Source {
+    /// path relative to the application folder
+    pub name: String,
+    pub content: String,
+}
+
+impl Source {
+    /// Line and column of a byte offset, both starting at 1
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.content.len());
+        let line_start = self.content[..offset]
+            .rfind('\n')
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        let line = self.content[..offset].matches('\n').count() + 1;
+        let column = self.content[line_start..offset].chars().count() + 1;
+        (line, column)
+    }
+}
+
+/// Every file read while parsing an application, to render diagnostics
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    sources: Vec<Source>,
+}
+
+impl SourceMap {
+    pub fn add(&mut self, name: impl Into<String>, content: impl Into<String>) -> FileId {
+        self.sources.push(Source {
+            name: name.into(),
+            content: content.into(),
+        });
+        FileId(self.sources.len() - 1)
+    }
+
+    pub fn get(&self, file: FileId) -> &Source {
+        &self.sources[file.0]
+    }
+
+    /// e.g. `common/login.ba:3:7`
+    pub fn location(&self, span: Span) -> String {
+        let source = self.get(span.file);
+        let (line, column) = source.position(span.start);
+        format!("{}:{}:{}", source.name, line, column)
+    }
+}
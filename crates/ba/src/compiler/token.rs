@@ -1,3 +1,6 @@
+use super::source::FileId;
+
+#[derive(Clone, Copy)]
 pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
@@ -32,21 +35,42 @@ impl std::fmt::Display for Token {
 
 #[derive(Debug, Clone, Copy)]
 pub struct Span {
+    pub file: FileId,
     // inclusive
     pub start: usize,
     // exclusive
     pub end: usize,
 }
 
+impl Span {
+    /// Span from the start of this one to the end of `other`, in the same file
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end,
+            ..self
+        }
+    }
+
+    /// Empty span at the start of this one
+    pub fn empty(self) -> Span {
+        Span {
+            end: self.start,
+            ..self
+        }
+    }
+}
+
 impl From<Span> for std::ops::Range<usize> {
     fn from(span: Span) -> Self {
         span.start..span.end
     }
 }
 
+/// Span in the main file
 impl From<std::ops::Range<usize>> for Span {
     fn from(range: std::ops::Range<usize>) -> Self {
         Self {
+            file: FileId::MAIN,
             start: range.start,
             end: range.end,
         }
@@ -66,7 +90,18 @@ impl std::ops::Index<Span> for str {
 pub enum TokenKind {
     // Operators
     Define,
+    Let,
     Eq,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    EqEq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
 
     // Actions
     Move,
@@ -82,18 +117,26 @@ pub enum TokenKind {
     PrintClipboard,
     ScrollUp,
     ScrollDown,
+    Repeat,
+    If,
+    Else,
+    Function,
+    Call,
+    Include,
 
     // Delimiters
     Whitespace,
     Comma,
     LBrace,
     RBrace,
+    LParen,
+    RParen,
     EOI, // end of instruction
     EOF, // end of file
 
     // Multiple characters
     Word,
-    Position,
+    Variable,
     String,
     Float,
     Comment,
@@ -106,7 +149,18 @@ pub enum TokenKind {
 macro_rules! TK {
     // Operators
     [def] => {$crate::compiler::token::TokenKind::Define};
+    [let] => {$crate::compiler::token::TokenKind::Let};
     [=] => {$crate::compiler::token::TokenKind::Eq};
+    [+] => {$crate::compiler::token::TokenKind::Plus};
+    [-] => {$crate::compiler::token::TokenKind::Minus};
+    [*] => {$crate::compiler::token::TokenKind::Star};
+    [/] => {$crate::compiler::token::TokenKind::Slash};
+    [==] => {$crate::compiler::token::TokenKind::EqEq};
+    [!=] => {$crate::compiler::token::TokenKind::NotEq};
+    [<] => {$crate::compiler::token::TokenKind::Lt};
+    [<=] => {$crate::compiler::token::TokenKind::LtEq};
+    [>] => {$crate::compiler::token::TokenKind::Gt};
+    [>=] => {$crate::compiler::token::TokenKind::GtEq};
 
     // Actions
     [Move] => {$crate::compiler::token::TokenKind::Move};
@@ -122,6 +176,12 @@ macro_rules! TK {
     [PrintClipboard] => {$crate::compiler::token::TokenKind::PrintClipboard};
     [ScrollUp] => {$crate::compiler::token::TokenKind::ScrollUp};
     [ScrollDown] => {$crate::compiler::token::TokenKind::ScrollDown};
+    [Repeat] => {$crate::compiler::token::TokenKind::Repeat};
+    [If] => {$crate::compiler::token::TokenKind::If};
+    [Else] => {$crate::compiler::token::TokenKind::Else};
+    [Function] => {$crate::compiler::token::TokenKind::Function};
+    [Call] => {$crate::compiler::token::TokenKind::Call};
+    [Include] => {$crate::compiler::token::TokenKind::Include};
 
 
     // Delimiters
@@ -129,12 +189,14 @@ macro_rules! TK {
     [,] => {$crate::compiler::token::TokenKind::Comma};
     [LBrace] => {$crate::compiler::token::TokenKind::LBrace};
     [RBrace] => {$crate::compiler::token::TokenKind::RBrace};
+    [LParen] => {$crate::compiler::token::TokenKind::LParen};
+    [RParen] => {$crate::compiler::token::TokenKind::RParen};
     [EOI] => {$crate::compiler::token::TokenKind::EOI};
     [EOF] => {$crate::compiler::token::TokenKind::EOF};
 
     // Multiple characters
     [Word] => {$crate::compiler::token::TokenKind::Word};
-    [Position] => {$crate::compiler::token::TokenKind::Position};
+    [Variable] => {$crate::compiler::token::TokenKind::Variable};
     [String] => {$crate::compiler::token::TokenKind::String};
     [Float] => {$crate::compiler::token::TokenKind::Float};
     [Comment] => {$crate::compiler::token::TokenKind::Comment};
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+/// Variables of a running script, by name without the leading `$`
+pub type Variables = HashMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    String(String),
+}
+
+impl Value {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::Int(_) => "integer",
+            Self::Float(_) => "float",
+            Self::String(_) => "string",
+        }
+    }
+
+    pub fn as_f64(&self) -> anyhow::Result<f64> {
+        match self {
+            Self::Int(int) => Ok(*int as f64),
+            Self::Float(float) => Ok(*float),
+            Self::String(_) => Err(anyhow::anyhow!(
+                "Expected a number, found the {} {}",
+                self.type_name(),
+                self
+            )),
+        }
+    }
+
+    /// Floats are rounded down
+    pub fn as_i32(&self) -> anyhow::Result<i32> {
+        Ok(self.as_f64()?.floor() as i32)
+    }
+
+    /// Number of iterations of a loop
+    pub fn as_count(&self) -> anyhow::Result<usize> {
+        match self {
+            Self::Int(int) if *int >= 0 => Ok(*int as usize),
+            _ => Err(anyhow::anyhow!(
+                "Expected a positive whole number, found the {} {}",
+                self.type_name(),
+                self
+            )),
+        }
+    }
+
+    /// Numbers are compared with numbers and strings with strings
+    pub fn compare(&self, other: &Value) -> anyhow::Result<std::cmp::Ordering> {
+        match (self, other) {
+            (Self::Int(lhs), Self::Int(rhs)) => Ok(lhs.cmp(rhs)),
+            (Self::String(lhs), Self::String(rhs)) => Ok(lhs.cmp(rhs)),
+            (Self::String(_), _) | (_, Self::String(_)) => Err(anyhow::anyhow!(
+                "Cannot compare the {} {} with the {} {}",
+                self.type_name(),
+                self,
+                other.type_name(),
+                other
+            )),
+            _ => self
+                .as_f64()?
+                .partial_cmp(&other.as_f64()?)
+                .ok_or_else(|| anyhow::anyhow!("Cannot compare {} with {}", self, other)),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Int(int) => write!(f, "{}", int),
+            Self::Float(float) => write!(f, "{}", float),
+            Self::String(string) => write!(f, "{}", string),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl std::fmt::Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Add => write!(f, "+"),
+            Self::Sub => write!(f, "-"),
+            Self::Mul => write!(f, "*"),
+            Self::Div => write!(f, "/"),
+        }
+    }
+}
+
+/// An arithmetic expression, evaluated each time the expression holding it is executed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Literal(Value),
+    Variable(String),
+    Negate(Box<Term>),
+    Binary(Box<Term>, Operator, Box<Term>),
+}
+
+impl Term {
+    #[cfg(test)]
+    pub fn int(int: i64) -> Self {
+        Self::Literal(Value::Int(int))
+    }
+
+    #[cfg(test)]
+    pub fn float(float: f64) -> Self {
+        Self::Literal(Value::Float(float))
+    }
+
+    pub fn string(string: impl Into<String>) -> Self {
+        Self::Literal(Value::String(string.into()))
+    }
+
+    /// Variables are looked up in the scopes in order
+    pub fn evaluate(&self, scopes: &[&Variables]) -> anyhow::Result<Value> {
+        match self {
+            Self::Literal(value) => Ok(value.clone()),
+            Self::Variable(name) => scopes
+                .iter()
+                .find_map(|variables| variables.get(name))
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Variable '${}' is not defined yet", name)),
+            Self::Negate(term) => match term.evaluate(scopes)? {
+                Value::Int(int) => int
+                    .checked_neg()
+                    .map(Value::Int)
+                    .ok_or_else(|| anyhow::anyhow!("Overflow in -{}", int)),
+                Value::Float(float) => Ok(Value::Float(-float)),
+                value @ Value::String(_) => Err(anyhow::anyhow!(
+                    "Cannot negate the {} {}",
+                    value.type_name(),
+                    value
+                )),
+            },
+            Self::Binary(lhs, operator, rhs) => {
+                apply(lhs.evaluate(scopes)?, *operator, rhs.evaluate(scopes)?)
+            }
+        }
+    }
+
+    /// Evaluates the term if it does not depend on any variable
+    pub fn constant(&self) -> Option<anyhow::Result<Value>> {
+        self.is_constant().then(|| self.evaluate(&[]))
+    }
+
+    fn is_constant(&self) -> bool {
+        match self {
+            Self::Literal(_) => true,
+            Self::Variable(_) => false,
+            Self::Negate(term) => term.is_constant(),
+            Self::Binary(lhs, _, rhs) => lhs.is_constant() && rhs.is_constant(),
+        }
+    }
+}
+
+fn apply(lhs: Value, operator: Operator, rhs: Value) -> anyhow::Result<Value> {
+    use Operator::*;
+    use Value::*;
+    match (lhs, operator, rhs) {
+        // concatenation as soon as one side is a string
+        (lhs @ String(_), Add, rhs) | (lhs, Add, rhs @ String(_)) => {
+            Ok(String(format!("{}{}", lhs, rhs)))
+        }
+        (_, Div, Int(0) | Float(0.0)) => Err(anyhow::anyhow!("Division by zero")),
+        (Int(lhs), operator, Int(rhs)) => {
+            let result = match operator {
+                Add => lhs.checked_add(rhs),
+                Sub => lhs.checked_sub(rhs),
+                Mul => lhs.checked_mul(rhs),
+                // whole division, like Rust
+                Div => lhs.checked_div(rhs),
+            };
+            result
+                .map(Int)
+                .ok_or_else(|| anyhow::anyhow!("Overflow in {} {} {}", lhs, operator, rhs))
+        }
+        (lhs @ (Int(_) | Float(_)), operator, rhs @ (Int(_) | Float(_))) => {
+            let (lhs, rhs) = (lhs.as_f64()?, rhs.as_f64()?);
+            Ok(Float(match operator {
+                Add => lhs + rhs,
+                Sub => lhs - rhs,
+                Mul => lhs * rhs,
+                Div => lhs / rhs,
+            }))
+        }
+        (lhs, operator, rhs) => Err(anyhow::anyhow!(
+            "Cannot apply '{}' to the {} {} and the {} {}",
+            operator,
+            lhs.type_name(),
+            lhs,
+            rhs.type_name(),
+            rhs
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary(lhs: Term, operator: Operator, rhs: Term) -> Term {
+        Term::Binary(Box::new(lhs), operator, Box::new(rhs))
+    }
+
+    #[test]
+    fn arithmetic() {
+        let variables = Variables::from([("x".to_string(), Value::Int(7))]);
+        let term = binary(
+            Term::Variable("x".to_string()),
+            Operator::Mul,
+            binary(Term::int(1), Operator::Add, Term::float(0.5)),
+        );
+        assert_eq!(term.evaluate(&[&variables]).unwrap(), Value::Float(10.5));
+        assert_eq!(
+            binary(Term::int(7), Operator::Div, Term::int(2))
+                .evaluate(&[&variables])
+                .unwrap(),
+            Value::Int(3)
+        );
+        assert_eq!(
+            binary(
+                Term::string("x = "),
+                Operator::Add,
+                Term::Variable("x".to_string())
+            )
+            .evaluate(&[&variables])
+            .unwrap(),
+            Value::String("x = 7".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_operations() {
+        let variables = Variables::new();
+        assert!(
+            binary(Term::int(1), Operator::Div, Term::int(0))
+                .evaluate(&[&variables])
+                .is_err()
+        );
+        assert!(
+            binary(Term::string("a"), Operator::Mul, Term::int(2))
+                .evaluate(&[&variables])
+                .is_err()
+        );
+        assert!(
+            Term::Variable("y".to_string())
+                .evaluate(&[&variables])
+                .is_err()
+        );
+        assert!(Term::Variable("y".to_string()).constant().is_none());
+        assert!(
+            Term::Negate(Box::new(Term::int(i64::MIN)))
+                .evaluate(&[&variables])
+                .is_err()
+        );
+    }
+}
@@ -1,5 +1,3 @@
-#![allow(dead_code)]
-
 use directories::ProjectDirs;
 use std::sync::OnceLock;
 
@@ -1,8 +1,9 @@
 use anyhow::Context;
-use inputbot::KeybdKey::{self, *};
-use std::{collections::HashMap, io::Read, path::Path, sync::OnceLock};
-
-static KEYMAP: OnceLock<KeyMap> = OnceLock::new();
+use inputbot::{
+    KeybdKey::{self, *},
+    KeyboardLayout,
+};
+use std::{collections::HashMap, io::Read, path::Path};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct KeyMap {
@@ -10,7 +11,13 @@ pub struct KeyMap {
 }
 
 impl KeyMap {
-    pub fn init(keymap_filepath: &Path) -> anyhow::Result<()> {
+    /// Reads a list of (name, key) pairs from a JSON file
+    pub fn load(keymap_filepath: &Path) -> anyhow::Result<Self> {
+        Ok(KeyMap::from(Self::read_entries(keymap_filepath)?))
+    }
+
+    /// Reads the (name, key) pairs of a JSON file in their order, duplicates included
+    pub fn read_entries(keymap_filepath: &Path) -> anyhow::Result<Vec<(String, KeybdKey)>> {
         let mut data = Vec::new();
         std::fs::OpenOptions::new()
             .create(false)
@@ -22,18 +29,16 @@ impl KeyMap {
             ))?
             .read_to_end(&mut data)?;
 
-        let data: Vec<(String, KeybdKey)> = serde_json::from_slice(&data)?;
-        KEYMAP.set(KeyMap::from(data)).unwrap();
-        Ok(())
-    }
-
-    pub fn get() -> &'static Self {
-        KEYMAP.get().unwrap()
+        Ok(serde_json::from_slice(&data)?)
     }
 
-    #[cfg(test)]
-    pub fn test_init() {
-        KEYMAP.set(KeyMap::from(&DEFAULT_KEYMAP[..])).unwrap();
+    /// Default names followed by the names of the given layout, as written by `ba new`
+    pub fn defaults_for(layout: &KeyboardLayout) -> Vec<(&'static str, KeybdKey)> {
+        DEFAULT_KEYMAP
+            .iter()
+            .copied()
+            .chain(layout.names())
+            .collect()
     }
 }
 
@@ -66,7 +71,8 @@ impl From<Vec<(String, KeybdKey)>> for KeyMap {
     }
 }
 
-pub static DEFAULT_KEYMAP: [(&str, KeybdKey); 151] = [
+/// Names that do not depend on the keyboard layout, the others are part of the layout profiles
+pub static DEFAULT_KEYMAP: [(&str, KeybdKey); 127] = [
     ("backspace", BackspaceKey),
     ("tab", TabKey),
     ("enter", EnterKey),
@@ -83,7 +89,6 @@ pub static DEFAULT_KEYMAP: [(&str, KeybdKey); 151] = [
     ("left", LeftKey),
     ("leftarrow", LeftKey),
     ("gauche", LeftKey),
-    ("flèchegauche", LeftKey),
     ("flechegauche", LeftKey),
     ("up", UpKey),
     ("uparrow", UpKey),
@@ -131,7 +136,7 @@ pub static DEFAULT_KEYMAP: [(&str, KeybdKey); 151] = [
     ("r", RKey),
     ("s", SKey),
     ("t", TKey),
-    ("u", VKey),
+    ("u", UKey),
     ("v", VKey),
     ("w", WKey),
     ("x", XKey),
@@ -195,27 +200,4 @@ pub static DEFAULT_KEYMAP: [(&str, KeybdKey); 151] = [
     ("voldown", VolumeDownKey),
     ("volumeup", VolumeUpKey),
     ("volup", VolumeUpKey),
-    ("virgule", CommaKey),
-    ("comma", CommaKey),
-    ("point", PeriodKey),
-    ("period", PeriodKey),
-    ("tiret", DashKey),
-    ("moins", DashKey),
-    ("dash", DashKey),
-    ("minus", DashKey),
-    ("eaccentgrave", OEM1),
-    ("eaccgrave", OEM1),
-    ("paragraphe", OEM2),
-    ("section", OEM2),
-    ("trema", OEM3),
-    ("apostrophe", OEM4),
-    ("aaccentgrave", OEM5),
-    ("aaccgrave", OEM5),
-    ("circonflexe", OEM6),
-    ("circumflex", OEM6),
-    ("eaccentaigu", OEM7),
-    ("eaccaigu", OEM7),
-    ("dollar", OEM8),
-    ("pluspetitque", OEM102),
-    ("lessthan", OEM102),
 ];
@@ -0,0 +1,234 @@
+use crate::{
+    compiler::{is_word, keyword_of},
+    mousemap::DEFAULT_MOUSEMAP,
+};
+use inputbot::{KeybdKey, MouseButton};
+use std::{collections::HashMap, fmt::Debug};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    /// the name cannot be used as written
+    Error,
+    /// the name works but probably not as intended
+    Warning,
+}
+
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Problem found with a name of a keymap or mousemap
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub level: Level,
+    pub file: &'static str,
+    pub name: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}: {}", self.level, self.file, self.message)
+    }
+}
+
+/// Checks the entries of a keymap and a mousemap, in the order they are written
+pub fn lint_maps(keys: &[(String, KeybdKey)], buttons: &[(String, MouseButton)]) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    check_names("keymap.json", keys, &mut findings);
+    check_names("mousemap.json", buttons, &mut findings);
+
+    // the keymap is looked up first
+    for (name, _) in keys.iter() {
+        let shadowed = buttons
+            .iter()
+            .map(|(name, button)| (name.as_str(), *button))
+            .chain(DEFAULT_MOUSEMAP.iter().copied())
+            .find(|(button_name, _)| button_name == name);
+        if let Some((_, button)) = shadowed {
+            findings.push(Finding {
+                level: Level::Warning,
+                file: "keymap.json",
+                name: name.clone(),
+                message: format!("'{}' shadows the mouse button {:?}", name, button),
+            });
+        }
+    }
+
+    check_letters(keys, &mut findings);
+    findings
+}
+
+fn check_names<T: Copy + PartialEq + Debug>(
+    file: &'static str,
+    entries: &[(String, T)],
+    findings: &mut Vec<Finding>,
+) {
+    let mut push = |level, name: &str, message| {
+        findings.push(Finding {
+            level,
+            file,
+            name: name.to_string(),
+            message,
+        })
+    };
+    let mut seen: HashMap<&str, T> = HashMap::new();
+    for (name, target) in entries.iter() {
+        if name.to_lowercase() != *name {
+            push(
+                Level::Error,
+                name,
+                format!(
+                    "'{}' contains uppercase characters and can never match, names are looked up in lowercase",
+                    name
+                ),
+            );
+        }
+        if let Some(keyword) = keyword_of(name) {
+            // keywords are case-sensitive, only the exact spelling clashes
+            match keyword == name {
+                true => push(
+                    Level::Error,
+                    name,
+                    format!("'{}' is read as the keyword '{}'", name, keyword),
+                ),
+                false => push(
+                    Level::Warning,
+                    name,
+                    format!(
+                        "'{}' must be written in lowercase, '{}' is read as a keyword",
+                        name, keyword
+                    ),
+                ),
+            }
+        } else if !is_word(name) {
+            push(
+                Level::Error,
+                name,
+                format!(
+                    "'{}' cannot be written in a script, names start with a letter followed by letters, digits or underscores",
+                    name
+                ),
+            );
+        }
+        match seen.insert(name, *target) {
+            Some(previous) if previous != *target => push(
+                Level::Error,
+                name,
+                format!(
+                    "'{}' maps to both {:?} and {:?}, only the last one is used",
+                    name, previous, target
+                ),
+            ),
+            Some(_) => push(
+                Level::Warning,
+                name,
+                format!("'{}' is defined more than once", name),
+            ),
+            None => (),
+        }
+    }
+}
+
+// single letters are expected to name the key of that letter
+fn check_letters(keys: &[(String, KeybdKey)], findings: &mut Vec<Finding>) {
+    let letters: Vec<(char, KeybdKey)> = keys
+        .iter()
+        .filter_map(
+            |(name, key)| match name.chars().collect::<Vec<char>>()[..] {
+                [c] if c.is_ascii_lowercase() => Some((c, *key)),
+                _ => None,
+            },
+        )
+        .collect();
+    for (idx, (letter, key)) in letters.iter().enumerate() {
+        if let Some((other, _)) = letters[idx + 1..]
+            .iter()
+            .find(|(other, other_key)| other != letter && other_key == key)
+        {
+            findings.push(Finding {
+                level: Level::Warning,
+                file: "keymap.json",
+                name: letter.to_string(),
+                message: format!("'{}' and '{}' both map to {:?}", letter, other, key),
+            });
+        } else if format!("{:?}", key) != format!("{}Key", letter.to_ascii_uppercase())
+            && !letters[..idx]
+                .iter()
+                .any(|(other, other_key)| other != letter && other_key == key)
+        {
+            findings.push(Finding {
+                level: Level::Warning,
+                file: "keymap.json",
+                name: letter.to_string(),
+                message: format!("'{}' maps to {:?}", letter, key),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Level, lint_maps};
+    use crate::{keymap::KeyMap, mousemap::DEFAULT_MOUSEMAP};
+    use inputbot::{KeybdKey::*, KeyboardLayout, MouseButton::*};
+
+    fn entries<T: Copy>(entries: &[(&str, T)]) -> Vec<(String, T)> {
+        entries
+            .iter()
+            .map(|(name, target)| (name.to_string(), *target))
+            .collect()
+    }
+
+    #[test]
+    fn defaults_are_clean() {
+        for layout in KeyboardLayout::all() {
+            let keys = entries(&KeyMap::defaults_for(layout));
+            let findings = lint_maps(&keys, &entries(&DEFAULT_MOUSEMAP));
+            assert_eq!(findings, vec![], "{}", layout);
+        }
+    }
+
+    #[test]
+    fn reports_broken_names() {
+        let keys = entries(&[
+            ("Esc", EscapeKey),
+            ("jump", SpaceKey),
+            ("jump", WKey),
+            ("ok", EnterKey),
+            ("ok", EnterKey),
+            ("lmb", AKey),
+            ("move", MKey),
+            ("let", LKey),
+            ("flèche", LeftKey),
+            ("u", VKey),
+            ("v", VKey),
+            ("q", AKey),
+        ]);
+        let buttons = entries(&[("fire", LeftButton), ("x1", X1Button)]);
+        let findings = lint_maps(&keys, &buttons);
+        let found: Vec<(Level, &str)> = findings
+            .iter()
+            .map(|finding| (finding.level, finding.name.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Level::Error, "Esc"),
+                (Level::Error, "jump"),
+                (Level::Warning, "ok"),
+                (Level::Warning, "move"),
+                (Level::Error, "let"),
+                (Level::Error, "flèche"),
+                (Level::Warning, "lmb"),
+                (Level::Warning, "u"),
+                (Level::Warning, "q"),
+            ]
+        );
+    }
+}
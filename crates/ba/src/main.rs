@@ -2,10 +2,10 @@ mod cli;
 mod compiler;
 mod dirs;
 mod keymap;
+mod lint;
 mod mousemap;
 
 use anyhow::Context;
-use std::ffi::CStr;
 use tracing::{info, level_filters::LevelFilter};
 
 use directories::ProjectDirs;
@@ -15,12 +15,6 @@ use tracing_subscriber::{
     layer::SubscriberExt,
 };
 
-use windows::Win32::UI::{
-    HiDpi::{DPI_AWARENESS_CONTEXT_SYSTEM_AWARE, SetThreadDpiAwarenessContext},
-    Input::KeyboardAndMouse::GetKeyboardLayoutNameA,
-    WindowsAndMessaging::{GetSystemMetrics, SYSTEM_METRICS_INDEX},
-};
-
 #[cfg(not(debug_assertions))]
 use tracing_subscriber::EnvFilter;
 
@@ -116,29 +110,7 @@ fn main() -> anyhow::Result<()> {
     tracing::subscriber::set_global_default(subscriber).expect("Failed to set global subscriber");
     info!("{}", local_time_string_pretty);
 
-    // check keyboard layout
-    let mut pwszklid = [0_u8; 9];
-    unsafe {
-        GetKeyboardLayoutNameA(&mut pwszklid).context("Failed to get keyboard layout")?;
-    }
-    let keyboard_layout = CStr::from_bytes_with_nul(&pwszklid)?.to_str()?;
-    if keyboard_layout != "0000100C" {
-        tracing::error!(
-            "Expected '0000100C' for keyboard layout, got '{}'",
-            keyboard_layout
-        );
-        Err(anyhow::anyhow!(
-            "Invalid keyboard layout, switch to Swiss-French"
-        ))?
-    }
-
-    // get the width and height of the primary montior, SetThreadDpiAwarenessContext fixes issues with scale
-    unsafe { SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_SYSTEM_AWARE) };
-    let width = unsafe { GetSystemMetrics(SYSTEM_METRICS_INDEX(0)) };
-    let height = unsafe { GetSystemMetrics(SYSTEM_METRICS_INDEX(1)) };
-    tracing::info!("Primary monitor detected - {width}x{height}");
-
-    if let Err(e) = cli::cli((width, height)) {
+    if let Err(e) = cli::cli() {
         tracing::error!("{e}");
         Err(e)?
     } else {
@@ -1,8 +1,6 @@
 use anyhow::Context;
 use inputbot::MouseButton::{self, *};
-use std::{collections::HashMap, io::Read, path::Path, sync::OnceLock};
-
-static MOUSEMAP: OnceLock<MouseMap> = OnceLock::new();
+use std::{collections::HashMap, io::Read, path::Path};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct MouseMap {
@@ -10,7 +8,13 @@ pub struct MouseMap {
 }
 
 impl MouseMap {
-    pub fn init(mousemap_filepath: &Path) -> anyhow::Result<()> {
+    /// Reads a list of (name, button) pairs from a JSON file
+    pub fn load(mousemap_filepath: &Path) -> anyhow::Result<Self> {
+        Ok(MouseMap::from(Self::read_entries(mousemap_filepath)?))
+    }
+
+    /// Reads the (name, button) pairs of a JSON file in their order, duplicates included
+    pub fn read_entries(mousemap_filepath: &Path) -> anyhow::Result<Vec<(String, MouseButton)>> {
         let mut data = Vec::new();
         std::fs::OpenOptions::new()
             .create(false)
@@ -22,18 +26,7 @@ impl MouseMap {
             ))?
             .read_to_end(&mut data)?;
 
-        let data: Vec<(String, MouseButton)> = serde_json::from_slice(&data)?;
-        MOUSEMAP.set(MouseMap::from(data)).unwrap();
-        Ok(())
-    }
-
-    pub fn get() -> &'static Self {
-        MOUSEMAP.get().unwrap()
-    }
-
-    #[cfg(test)]
-    pub fn test_init() {
-        MOUSEMAP.set(MouseMap::from(&DEFAULT_MOUSEMAP[..])).unwrap();
+        Ok(serde_json::from_slice(&data)?)
     }
 }
 
@@ -0,0 +1,134 @@
+use crate::{
+    hotkey::{Hotkey, HotkeyHandle},
+    layout::KeyboardLayout,
+    public::{KeybdKey, MouseButton},
+};
+use std::sync::Arc;
+
+/// Everything the interpreter needs from the operating system in order to synthesize
+/// and observe user input, implemented once per platform.
+pub trait InputBackend: Send + Sync {
+    /// Human readable name of the backend, used for logging
+    fn name(&self) -> &'static str;
+
+    /// Presses a given `KeybdKey`. Note: this means the key will remain in the down
+    /// position. You must manually call release to create a full 'press'.
+    fn key_press(&self, key: KeybdKey);
+
+    /// Releases a given `KeybdKey`. This means the key would be in the up position.
+    fn key_release(&self, key: KeybdKey);
+
+    /// Presses then releases a given `KeybdKey`.
+    fn key_tap(&self, key: KeybdKey);
+
+    /// Presses the given keys in order then releases them in reverse order, e.g. Ctrl+Shift+S.
+    /// Backends able to do so send every input at once.
+    fn key_chord(&self, keys: &[KeybdKey]) {
+        keys.iter().for_each(|key| self.key_press(*key));
+        keys.iter().rev().for_each(|key| self.key_release(*key));
+    }
+
+    /// Returns true if a given `KeybdKey` is currently pressed (in the down position).
+    fn key_is_pressed(&self, key: KeybdKey) -> bool;
+
+    /// Returns true if a keyboard key which supports toggling (ScrollLock, NumLock,
+    /// CapsLock) is on.
+    fn key_is_toggled(&self, key: KeybdKey) -> bool;
+
+    /// Presses a given `MouseButton`. Note: this means the button will remain in the down
+    /// position. You must manually call release to create a full 'click'.
+    fn mouse_press(&self, button: MouseButton);
+
+    /// Releases a given `MouseButton`. This means the button would be in the up position.
+    fn mouse_release(&self, button: MouseButton);
+
+    /// Presses then releases a given `MouseButton`.
+    fn mouse_tap(&self, button: MouseButton);
+
+    /// Returns true if a given `MouseButton` is currently pressed (in the down position).
+    fn mouse_is_pressed(&self, button: MouseButton) -> bool;
+
+    /// Returns the absolute position of the mouse cursor.
+    fn cursor_pos(&self) -> (i32, i32);
+
+    /// Moves the mouse to a given position based on absolute coordinates. The top left
+    /// corner of the screen is (0, 0).
+    fn move_abs(&self, x: i32, y: i32);
+
+    /// Moves the mouse relative to its current position by a given amount of pixels.
+    fn move_rel(&self, dx: i32, dy: i32) {
+        let (x, y) = self.cursor_pos();
+        self.move_abs(x + dx, y + dy);
+    }
+
+    /// Scrolls the mouse wheel vertically by a given unscaled amount,
+    /// positive values scroll up and negative values scroll down.
+    fn scroll_ver(&self, delta: i32);
+
+    /// Scrolls the mouse wheel horizontally by a given unscaled amount.
+    fn scroll_hor(&self, delta: i32);
+
+    /// Types the given string at once, independently of the keyboard layout.
+    fn send_sequence(&self, input: &str);
+
+    /// Attempts to collect the clipboard's content to a string,
+    /// returns None in case it fails
+    fn get_clipboard_string(&self) -> Option<String>;
+
+    /// Width and height of the primary monitor in pixels
+    fn primary_monitor_size(&self) -> anyhow::Result<(i32, i32)>;
+
+    /// Identifier of the active keyboard layout, if the backend is able to query it
+    fn keyboard_layout(&self) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Uses the given layout to find the keys whose character depends on it,
+    /// only needed by backends that cannot identify these keys by their position
+    fn set_keyboard_layout(&self, _layout: &'static KeyboardLayout) {}
+
+    /// Spawns a thread that waits for `hotkey` to be pressed once, then calls `callback`.
+    /// The callback is not called if the returned handle is dropped beforehand.
+    fn listen_once(
+        &self,
+        hotkey: Hotkey,
+        callback: Box<dyn FnOnce() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle>;
+
+    /// Spawns a thread that calls `callback` every time `hotkey` is pressed,
+    /// until the returned handle is dropped.
+    fn detached_hotkey(
+        &self,
+        hotkey: Hotkey,
+        callback: Box<dyn Fn() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle>;
+
+    /// Observes `button` through a low-level hook and calls `callback` on a separate thread
+    /// every time it is pressed, until the returned handle is dropped. When `swallow` is set,
+    /// the press and the release of the button never reach the other applications. Fails when
+    /// the hook cannot be installed, for instance when another client already grabbed the button.
+    fn hook_mouse(
+        &self,
+        button: MouseButton,
+        swallow: bool,
+        callback: Box<dyn Fn() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle>;
+}
+
+/// Returns the backend matching the platform this binary was compiled for.
+pub fn default_backend() -> anyhow::Result<Arc<dyn InputBackend>> {
+    #[cfg(windows)]
+    {
+        Ok(Arc::new(crate::windows::WindowsBackend::new()))
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Ok(Arc::new(crate::x11::X11Backend::new()?))
+    }
+    #[cfg(not(any(windows, target_os = "linux")))]
+    {
+        Err(anyhow::anyhow!(
+            "No input backend is available for this platform"
+        ))
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Source of time for everything that waits while a script runs, so that scripts
+/// can be executed against a simulated timeline instead of the wall clock.
+pub trait Clock: Send + Sync {
+    /// Time elapsed since the creation of the clock.
+    fn now(&self) -> Duration;
+
+    /// Suspends the calling thread for the given duration of script time.
+    fn sleep(&self, duration: Duration);
+
+    /// Waits between two checks of a polling loop, this is not part of the script's
+    /// timeline and therefore never advances a simulated clock.
+    fn poll(&self, interval: Duration) {
+        std::thread::sleep(interval)
+    }
+}
+
+/// The wall clock
+pub struct RealClock {
+    start: Instant,
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for RealClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration)
+    }
+}
+
+/// A virtual clock, sleeping advances the virtual time instantly instead of blocking
+#[derive(Default)]
+pub struct SimulatedClock {
+    // nanoseconds
+    now: AtomicU64,
+}
+
+impl SimulatedClock {
+    // real time spent in `poll`, keeps polling loops from spinning
+    const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the virtual time forward without sleeping
+    pub fn advance(&self, duration: Duration) {
+        self.now
+            .fetch_add(duration.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.now.load(Ordering::SeqCst))
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+
+    fn poll(&self, _interval: Duration) {
+        std::thread::sleep(Self::POLL_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulated_sleep_is_instant() {
+        let clock = SimulatedClock::new();
+        let start = Instant::now();
+        clock.sleep(Duration::from_secs(600));
+        clock.sleep(Duration::from_millis(50));
+        assert_eq!(clock.now(), Duration::from_millis(600_050));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
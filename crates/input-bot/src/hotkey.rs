@@ -0,0 +1,198 @@
+use crate::public::{KeybdKey, MouseButton};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// Modifier keys that have to be held down for a `Hotkey` to trigger,
+/// the left and right variants of a modifier are not told apart
+#[derive(Debug, Default, Eq, PartialEq, Hash, Copy, Clone, Serialize, Deserialize)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub win: bool,
+}
+
+impl Modifiers {
+    /// Returns the modifier matching the given key, None if the key is not a modifier
+    pub fn from_key(key: KeybdKey) -> Option<Self> {
+        let none = Self::default();
+        match key {
+            KeybdKey::LControlKey | KeybdKey::RControlKey => Some(Self { ctrl: true, ..none }),
+            KeybdKey::LAltKey | KeybdKey::RAltKey => Some(Self { alt: true, ..none }),
+            KeybdKey::LShiftKey | KeybdKey::RShiftKey => Some(Self {
+                shift: true,
+                ..none
+            }),
+            KeybdKey::LSuper | KeybdKey::RSuper => Some(Self { win: true, ..none }),
+            _ => None,
+        }
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            ctrl: self.ctrl || other.ctrl,
+            alt: self.alt || other.alt,
+            shift: self.shift || other.shift,
+            win: self.win || other.win,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl std::fmt::Display for Modifiers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names = [
+            (self.ctrl, "ctrl"),
+            (self.alt, "alt"),
+            (self.shift, "shift"),
+            (self.win, "win"),
+        ];
+        let held: Vec<&str> = names
+            .into_iter()
+            .filter_map(|(held, name)| held.then_some(name))
+            .collect();
+        write!(f, "{}", held.join("+"))
+    }
+}
+
+/// Key registered as a hotkey along with the modifiers it requires, e.g. Ctrl+Alt+1
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone, Serialize, Deserialize)]
+pub struct Hotkey {
+    pub modifiers: Modifiers,
+    pub key: KeybdKey,
+}
+
+impl Hotkey {
+    pub fn new(modifiers: Modifiers, key: KeybdKey) -> Self {
+        Self { modifiers, key }
+    }
+}
+
+impl From<KeybdKey> for Hotkey {
+    fn from(key: KeybdKey) -> Self {
+        Self::new(Modifiers::default(), key)
+    }
+}
+
+impl std::fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.is_empty() {
+            write!(f, "{:?}", self.key)
+        } else {
+            write!(f, "{}+{:?}", self.modifiers, self.key)
+        }
+    }
+}
+
+/// Input observed by a registered hotkey, either a key combination or a mouse button
+/// watched through a low-level hook
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub enum Trigger {
+    Key(Hotkey),
+    Mouse(MouseButton),
+}
+
+impl From<Hotkey> for Trigger {
+    fn from(hotkey: Hotkey) -> Self {
+        Self::Key(hotkey)
+    }
+}
+
+impl From<KeybdKey> for Trigger {
+    fn from(key: KeybdKey) -> Self {
+        Self::Key(key.into())
+    }
+}
+
+impl From<MouseButton> for Trigger {
+    fn from(button: MouseButton) -> Self {
+        Self::Mouse(button)
+    }
+}
+
+impl std::fmt::Display for Trigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Key(hotkey) => write!(f, "{}", hotkey),
+            Self::Mouse(button) => write!(f, "{:?}", button),
+        }
+    }
+}
+
+/// Identifier of a registered hotkey, unique for the lifetime of the process
+#[derive(Debug, Eq, PartialEq, Hash, Copy, Clone)]
+pub struct HotkeyId(pub(crate) i32);
+
+impl HotkeyId {
+    // applications may only use the ids 0x0000 through 0xBFFF with RegisterHotKey
+    const MAX: i32 = 0xBFFF;
+
+    /// Hands out the next free id
+    pub fn next() -> Self {
+        static NEXT: AtomicI32 = AtomicI32::new(1);
+        let id = NEXT
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |id| {
+                Some(if id >= Self::MAX { 1 } else { id + 1 })
+            })
+            // the closure never returns None
+            .unwrap();
+        Self(id)
+    }
+}
+
+/// Keeps a hotkey registered, it is unregistered when the handle is cancelled or dropped
+#[must_use = "the hotkey is unregistered as soon as its handle is dropped"]
+pub struct HotkeyHandle {
+    id: HotkeyId,
+    trigger: Trigger,
+    unregister: Option<Box<dyn FnOnce() + Send + 'static>>,
+}
+
+impl HotkeyHandle {
+    /// `unregister` is called once, when the handle is cancelled or dropped
+    pub fn new(
+        id: HotkeyId,
+        trigger: impl Into<Trigger>,
+        unregister: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        Self {
+            id,
+            trigger: trigger.into(),
+            unregister: Some(Box::new(unregister)),
+        }
+    }
+
+    pub fn id(&self) -> HotkeyId {
+        self.id
+    }
+
+    pub fn trigger(&self) -> Trigger {
+        self.trigger
+    }
+
+    /// Unregisters the hotkey right away
+    pub fn cancel(self) {
+        drop(self)
+    }
+}
+
+impl Drop for HotkeyHandle {
+    fn drop(&mut self) {
+        if let Some(unregister) = self.unregister.take() {
+            tracing::debug!("Unregistering HotKey '{}' ({:?})", self.trigger, self.id);
+            unregister();
+        }
+    }
+}
+
+impl std::fmt::Debug for HotkeyHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HotkeyHandle")
+            .field("id", &self.id)
+            .field("trigger", &self.trigger)
+            .finish()
+    }
+}
@@ -5,7 +5,7 @@ use crate::public::{
 
 impl From<KeybdKey> for u64 {
     // https://docs.microsoft.com/en-us/windows/win32/inputdev/virtual-key-codes?redirectedfrom=MSDN
-    // modified for swiss-french keyboard
+    // the OEM codes are the same for every layout, see layout.rs for the characters they type
     fn from(key: KeybdKey) -> u64 {
         match key {
             BackspaceKey => 0x08,
@@ -115,30 +115,20 @@ impl From<KeybdKey> for u64 {
             MediaStopKey => 0xB2,
             MediaPlayPauseKey => 0xB3,
 
-            // http://kbdlayout.info/KBDSF/virtualkeys
-            // OEM COMMA
+            // VK_OEM_COMMA
             CommaKey => 0xBC,
-            // OEM PERIOD
+            // VK_OEM_PERIOD
             PeriodKey => 0xBE,
-            // OEM MINUS
+            // VK_OEM_MINUS
             DashKey => 0xBD,
-            // è
             OEM1 => 0xBA,
-            // §
             OEM2 => 0xBF,
-            // e.g. ï
             OEM3 => 0xC0,
-            // '
             OEM4 => 0xDB,
-            // à
             OEM5 => 0xDC,
-            // ^
             OEM6 => 0xDD,
-            // é
             OEM7 => 0xDE,
-            // $
             OEM8 => 0xDF,
-            // <
             OEM102 => 0xE2,
 
             OtherKey(code) => code,
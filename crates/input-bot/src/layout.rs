@@ -0,0 +1,383 @@
+use crate::public::KeybdKey::{self, *};
+
+/// Key whose character depends on the keyboard layout, on Windows its virtual-key code
+/// is the same for every layout, on X11 the key is found through the character it types
+#[derive(Debug, PartialEq)]
+pub struct LayoutKey {
+    pub key: KeybdKey,
+    /// keysym of the character typed by the key without modifiers
+    pub keysym: u32,
+    /// lowercase names of the key in scripts
+    pub names: &'static [&'static str],
+}
+
+/// Describes the keys of a keyboard layout that differ from one layout to another
+#[derive(Debug, PartialEq)]
+pub struct KeyboardLayout {
+    /// used to select the layout, e.g. "ch-fr"
+    pub name: &'static str,
+    pub description: &'static str,
+    /// identifier returned by `GetKeyboardLayoutNameA` on Windows
+    pub klid: &'static str,
+    /// layout and variant of the XKB configuration on X11, e.g. "ch(fr)"
+    pub xkb: &'static str,
+    pub keys: &'static [LayoutKey],
+}
+
+impl KeyboardLayout {
+    /// Every supported layout
+    pub fn all() -> &'static [KeyboardLayout] {
+        &LAYOUTS
+    }
+
+    /// Layout selected by its name, ignoring the case
+    pub fn by_name(name: &str) -> Option<&'static Self> {
+        LAYOUTS
+            .iter()
+            .find(|layout| layout.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Layout matching an identifier returned by `InputBackend::keyboard_layout`
+    pub fn by_id(id: &str) -> Option<&'static Self> {
+        LAYOUTS
+            .iter()
+            .find(|layout| layout.klid.eq_ignore_ascii_case(id) || layout.xkb == id)
+    }
+
+    /// Keysym of the character the key types with this layout, if it depends on it
+    pub fn keysym(&self, key: KeybdKey) -> Option<u32> {
+        self.keys
+            .iter()
+            .find(|layout_key| layout_key.key == key)
+            .map(|layout_key| layout_key.keysym)
+    }
+
+    /// Every name of the layout with its key
+    pub fn names(&self) -> impl Iterator<Item = (&'static str, KeybdKey)> {
+        self.keys
+            .iter()
+            .flat_map(|layout_key| layout_key.names.iter().map(|name| (*name, layout_key.key)))
+    }
+}
+
+impl std::fmt::Display for KeyboardLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name, self.description)
+    }
+}
+
+/// Layout the scripts were written for before layouts could be selected
+pub static SWISS_FRENCH: &KeyboardLayout = &LAYOUTS[0];
+
+// http://kbdlayout.info, keysyms from
+// https://gitlab.freedesktop.org/xorg/proto/xorgproto/-/blob/master/include/X11/keysymdef.h
+static LAYOUTS: [KeyboardLayout; 4] = [
+    KeyboardLayout {
+        name: "ch-fr",
+        description: "Swiss French",
+        klid: "0000100C",
+        xkb: "ch(fr)",
+        keys: &[
+            LayoutKey {
+                key: CommaKey,
+                keysym: 0x002C,
+                names: &["virgule", "comma"],
+            },
+            LayoutKey {
+                key: PeriodKey,
+                keysym: 0x002E,
+                names: &["point", "period"],
+            },
+            LayoutKey {
+                key: DashKey,
+                keysym: 0x002D,
+                names: &["tiret", "moins", "dash", "minus"],
+            },
+            LayoutKey {
+                key: OEM1,
+                keysym: 0x00E8,
+                names: &["eaccentgrave", "eaccgrave"],
+            },
+            LayoutKey {
+                key: OEM2,
+                keysym: 0x00A7,
+                names: &["paragraphe", "section"],
+            },
+            LayoutKey {
+                key: OEM3,
+                keysym: 0xFE57,
+                names: &["trema"],
+            },
+            LayoutKey {
+                key: OEM4,
+                keysym: 0x0027,
+                names: &["apostrophe"],
+            },
+            LayoutKey {
+                key: OEM5,
+                keysym: 0x00E0,
+                names: &["aaccentgrave", "aaccgrave"],
+            },
+            LayoutKey {
+                key: OEM6,
+                keysym: 0xFE52,
+                names: &["circonflexe", "circumflex"],
+            },
+            LayoutKey {
+                key: OEM7,
+                keysym: 0x00E9,
+                names: &["eaccentaigu", "eaccaigu"],
+            },
+            LayoutKey {
+                key: OEM8,
+                keysym: 0x0024,
+                names: &["dollar"],
+            },
+            LayoutKey {
+                key: OEM102,
+                keysym: 0x003C,
+                names: &["pluspetitque", "lessthan"],
+            },
+        ],
+    },
+    KeyboardLayout {
+        name: "us",
+        description: "US",
+        klid: "00000409",
+        xkb: "us",
+        keys: &[
+            LayoutKey {
+                key: CommaKey,
+                keysym: 0x002C,
+                names: &["comma"],
+            },
+            LayoutKey {
+                key: PeriodKey,
+                keysym: 0x002E,
+                names: &["period"],
+            },
+            LayoutKey {
+                key: DashKey,
+                keysym: 0x002D,
+                names: &["dash", "minus"],
+            },
+            LayoutKey {
+                key: OEM1,
+                keysym: 0x003B,
+                names: &["semicolon"],
+            },
+            LayoutKey {
+                key: OEM2,
+                keysym: 0x002F,
+                names: &["slash"],
+            },
+            LayoutKey {
+                key: OEM3,
+                keysym: 0x0060,
+                names: &["grave", "backtick"],
+            },
+            LayoutKey {
+                key: OEM4,
+                keysym: 0x005B,
+                names: &["leftbracket"],
+            },
+            LayoutKey {
+                key: OEM5,
+                keysym: 0x005C,
+                names: &["backslash"],
+            },
+            LayoutKey {
+                key: OEM6,
+                keysym: 0x005D,
+                names: &["rightbracket"],
+            },
+            LayoutKey {
+                key: OEM7,
+                keysym: 0x0027,
+                names: &["apostrophe", "quote"],
+            },
+        ],
+    },
+    KeyboardLayout {
+        name: "de",
+        description: "German",
+        klid: "00000407",
+        xkb: "de",
+        keys: &[
+            LayoutKey {
+                key: CommaKey,
+                keysym: 0x002C,
+                names: &["komma", "comma"],
+            },
+            LayoutKey {
+                key: PeriodKey,
+                keysym: 0x002E,
+                names: &["punkt", "period"],
+            },
+            LayoutKey {
+                key: DashKey,
+                keysym: 0x002D,
+                names: &["bindestrich", "dash", "minus"],
+            },
+            LayoutKey {
+                key: OEM1,
+                keysym: 0x00FC,
+                names: &["ue", "uumlaut"],
+            },
+            LayoutKey {
+                key: OEM2,
+                keysym: 0x0023,
+                names: &["raute", "hash"],
+            },
+            LayoutKey {
+                key: OEM3,
+                keysym: 0x00F6,
+                names: &["oe", "oumlaut"],
+            },
+            LayoutKey {
+                key: OEM4,
+                keysym: 0x00DF,
+                names: &["eszett", "sz"],
+            },
+            LayoutKey {
+                key: OEM5,
+                keysym: 0xFE52,
+                names: &["zirkumflex", "circumflex"],
+            },
+            LayoutKey {
+                key: OEM6,
+                keysym: 0xFE51,
+                names: &["akut", "acute"],
+            },
+            LayoutKey {
+                key: OEM7,
+                keysym: 0x00E4,
+                names: &["ae", "aumlaut"],
+            },
+            LayoutKey {
+                key: OEM102,
+                keysym: 0x003C,
+                names: &["kleinerals", "lessthan"],
+            },
+        ],
+    },
+    KeyboardLayout {
+        name: "fr",
+        description: "French",
+        klid: "0000040C",
+        xkb: "fr",
+        keys: &[
+            LayoutKey {
+                key: CommaKey,
+                keysym: 0x002C,
+                names: &["virgule", "comma"],
+            },
+            LayoutKey {
+                key: PeriodKey,
+                keysym: 0x003B,
+                names: &["pointvirgule", "semicolon"],
+            },
+            LayoutKey {
+                key: OEM1,
+                keysym: 0x0024,
+                names: &["dollar"],
+            },
+            LayoutKey {
+                key: OEM2,
+                keysym: 0x003A,
+                names: &["deuxpoints", "colon"],
+            },
+            LayoutKey {
+                key: OEM3,
+                keysym: 0x00F9,
+                names: &["uaccentgrave", "uaccgrave"],
+            },
+            LayoutKey {
+                key: OEM4,
+                keysym: 0x0029,
+                names: &["parenthesefermante", "rightparen"],
+            },
+            LayoutKey {
+                key: OEM5,
+                keysym: 0x002A,
+                names: &["asterisque", "asterisk"],
+            },
+            LayoutKey {
+                key: OEM6,
+                keysym: 0xFE52,
+                names: &["circonflexe", "circumflex"],
+            },
+            LayoutKey {
+                key: OEM7,
+                keysym: 0x00B2,
+                names: &["exposantdeux", "twosuperior"],
+            },
+            LayoutKey {
+                key: OEM8,
+                keysym: 0x0021,
+                names: &["exclamation"],
+            },
+            LayoutKey {
+                key: OEM102,
+                keysym: 0x003C,
+                names: &["pluspetitque", "lessthan"],
+            },
+            // the number row types these characters without shift
+            LayoutKey {
+                key: Numrow2Key,
+                keysym: 0x00E9,
+                names: &["eaccentaigu", "eaccaigu"],
+            },
+            LayoutKey {
+                key: Numrow7Key,
+                keysym: 0x00E8,
+                names: &["eaccentgrave", "eaccgrave"],
+            },
+            LayoutKey {
+                key: Numrow9Key,
+                keysym: 0x00E7,
+                names: &["ccedille"],
+            },
+            LayoutKey {
+                key: Numrow0Key,
+                keysym: 0x00E0,
+                names: &["aaccentgrave", "aaccgrave"],
+            },
+        ],
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyboardLayout, SWISS_FRENCH};
+    use crate::KeybdKey;
+    use std::collections::HashSet;
+
+    #[test]
+    fn layouts_are_consistent() {
+        for layout in KeyboardLayout::all() {
+            let mut names = HashSet::new();
+            for (name, _) in layout.names() {
+                assert_eq!(name, name.to_lowercase(), "in {}", layout);
+                assert!(names.insert(name), "'{}' repeated in {}", name, layout);
+            }
+            let mut keys = HashSet::new();
+            for layout_key in layout.keys {
+                assert!(keys.insert(layout_key.key), "{:?} repeated", layout_key.key);
+            }
+        }
+    }
+
+    #[test]
+    fn finds_layouts() {
+        assert_eq!(KeyboardLayout::by_id("0000100c"), Some(SWISS_FRENCH));
+        assert_eq!(KeyboardLayout::by_id("ch(fr)"), Some(SWISS_FRENCH));
+        assert_eq!(
+            KeyboardLayout::by_name("DE").map(|l| l.klid),
+            Some("00000407")
+        );
+        assert_eq!(KeyboardLayout::by_name("dvorak"), None);
+        assert_eq!(SWISS_FRENCH.keysym(KeybdKey::OEM8), Some(0x0024));
+        assert_eq!(SWISS_FRENCH.keysym(KeybdKey::AKey), None);
+    }
+}
@@ -1,5 +1,29 @@
 mod public;
 pub use crate::public::*;
 
+mod backend;
+pub use crate::backend::*;
+
+mod clock;
+pub use crate::clock::*;
+
+mod hotkey;
+pub use crate::hotkey::*;
+
+mod inputs;
+
+mod layout;
+pub use crate::layout::*;
+
+mod recording;
+pub use crate::recording::*;
+
+#[cfg(windows)]
 mod windows;
+#[cfg(windows)]
 pub use crate::windows::*;
+
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+pub use crate::x11::*;
@@ -148,7 +148,3 @@ pub enum MouseButton {
     MousewheelDown,
     OtherButton(u32),
 }
-
-pub struct MouseCursor;
-
-pub struct MouseWheel;
@@ -0,0 +1,475 @@
+use crate::{
+    Clock, Hotkey, HotkeyHandle, HotkeyId, InputBackend, KeyboardLayout, RealClock, Trigger,
+    public::{KeybdKey, MouseButton},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Condvar, Mutex, MutexGuard, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A single input synthesized through a `RecordingBackend`
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    KeyDown(KeybdKey),
+    KeyUp(KeybdKey),
+    MouseDown(MouseButton),
+    MouseUp(MouseButton),
+    MoveAbs(i32, i32),
+    WheelVer(i32),
+    WheelHor(i32),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedEvent {
+    /// time given by the clock of the backend
+    pub at: Duration,
+    pub event: InputEvent,
+}
+
+/// In-memory implementation of `InputBackend`, nothing reaches the operating system,
+/// every synthesized input is appended to an inspectable event log instead.
+/// Hotkey presses, hooked mouse buttons and clipboard contents are simulated on demand.
+pub struct RecordingBackend {
+    clock: Arc<dyn Clock>,
+    // shared with the handles of the hotkeys
+    state: Arc<Mutex<State>>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    events: Vec<RecordedEvent>,
+    keys_down: HashSet<KeybdKey>,
+    keys_toggled: HashSet<KeybdKey>,
+    buttons_down: HashSet<MouseButton>,
+    cursor: (i32, i32),
+    clipboard: Option<String>,
+    monitor_size: (i32, i32),
+    // identifier returned by `keyboard_layout`
+    layout_id: Option<String>,
+    selected_layout: Option<&'static KeyboardLayout>,
+    hotkeys: HashMap<Trigger, Vec<(HotkeyId, mpsc::Sender<()>)>>,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self {
+            clock: Arc::new(RealClock::new()),
+            state: Arc::new(Mutex::new(State {
+                monitor_size: (1920, 1080),
+                ..Default::default()
+            })),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Timestamps events using the given clock instead of the wall clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Clock the events are timestamped with
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    pub fn with_monitor_size(self, width: i32, height: i32) -> Self {
+        self.lock().monitor_size = (width, height);
+        self
+    }
+
+    /// Reports the given identifier as the active keyboard layout
+    pub fn with_keyboard_layout(self, id: &str) -> Self {
+        self.lock().layout_id = Some(id.to_string());
+        self
+    }
+
+    /// Layout last given to `set_keyboard_layout`
+    pub fn selected_layout(&self) -> Option<&'static KeyboardLayout> {
+        self.lock().selected_layout
+    }
+
+    /// Returns a copy of every event recorded so far
+    pub fn events(&self) -> Vec<RecordedEvent> {
+        self.lock().events.clone()
+    }
+
+    /// Returns every event recorded so far, without timestamps
+    pub fn inputs(&self) -> Vec<InputEvent> {
+        self.lock()
+            .events
+            .iter()
+            .map(|recorded| recorded.event.clone())
+            .collect()
+    }
+
+    pub fn clear_events(&self) {
+        self.lock().events.clear();
+    }
+
+    /// Sets the value returned by `get_clipboard_string`
+    pub fn set_clipboard(&self, content: Option<&str>) {
+        self.lock().clipboard = content.map(str::to_string);
+    }
+
+    /// Sets the value returned by `key_is_toggled` for the given key
+    pub fn set_toggled(&self, key: KeybdKey, toggled: bool) {
+        let mut state = self.lock();
+        if toggled {
+            state.keys_toggled.insert(key);
+        } else {
+            state.keys_toggled.remove(&key);
+        }
+    }
+
+    /// Returns true if a hotkey is currently listening for the given key combination
+    /// or mouse button
+    pub fn is_listening(&self, trigger: impl Into<Trigger>) -> bool {
+        self.lock()
+            .hotkeys
+            .get(&trigger.into())
+            .is_some_and(|senders| !senders.is_empty())
+    }
+
+    /// Simulates a press of the given hotkey or mouse button, returns the number of
+    /// listeners notified. Like on a real keyboard, a hotkey only triggers for its exact
+    /// modifiers.
+    pub fn trigger_hotkey(&self, trigger: impl Into<Trigger>) -> usize {
+        let trigger = trigger.into();
+        let mut state = self.lock();
+        let Some(senders) = state.hotkeys.get_mut(&trigger) else {
+            tracing::debug!("No listener for simulated HotKey '{}'", trigger);
+            return 0;
+        };
+        // listeners that went away are forgotten
+        senders.retain(|(_, sender)| sender.send(()).is_ok());
+        senders.len()
+    }
+
+    /// Blocks until `condition` holds for the recorded events or `timeout` elapses,
+    /// returns whether the condition was met
+    pub fn wait_until<F: Fn(&[RecordedEvent]) -> bool>(
+        &self,
+        condition: F,
+        timeout: Duration,
+    ) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.lock();
+        loop {
+            if condition(&state.events) {
+                return true;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            state = self
+                .changed
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|err| err.into_inner())
+                .0;
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn record(&self, events: impl IntoIterator<Item = InputEvent>) {
+        let at = self.clock.now();
+        let mut state = self.lock();
+        for event in events {
+            match event {
+                InputEvent::KeyDown(key) => {
+                    state.keys_down.insert(key);
+                }
+                InputEvent::KeyUp(key) => {
+                    state.keys_down.remove(&key);
+                }
+                InputEvent::MouseDown(button) => {
+                    state.buttons_down.insert(button);
+                }
+                InputEvent::MouseUp(button) => {
+                    state.buttons_down.remove(&button);
+                }
+                InputEvent::MoveAbs(x, y) => state.cursor = (x, y),
+                _ => (),
+            }
+            tracing::trace!("Recorded '{:?}'", event);
+            state.events.push(RecordedEvent { at, event });
+        }
+        self.changed.notify_all();
+    }
+
+    // The hotkey stops listening once the returned handle is dropped, which in turn
+    // disconnects the receiver
+    fn listen(&self, trigger: impl Into<Trigger>) -> (HotkeyHandle, mpsc::Receiver<()>) {
+        let trigger = trigger.into();
+        let id = HotkeyId::next();
+        let (sender, receiver) = mpsc::channel();
+        self.lock()
+            .hotkeys
+            .entry(trigger)
+            .or_default()
+            .push((id, sender));
+        let state = self.state.clone();
+        let handle = HotkeyHandle::new(id, trigger, move || {
+            let mut state = state.lock().unwrap_or_else(|err| err.into_inner());
+            if let Some(senders) = state.hotkeys.get_mut(&trigger) {
+                senders.retain(|(other, _)| *other != id);
+            }
+        });
+        (handle, receiver)
+    }
+}
+
+impl Default for RecordingBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputBackend for RecordingBackend {
+    fn name(&self) -> &'static str {
+        "recording"
+    }
+
+    fn key_press(&self, key: KeybdKey) {
+        self.record([InputEvent::KeyDown(key)]);
+    }
+
+    fn key_release(&self, key: KeybdKey) {
+        self.record([InputEvent::KeyUp(key)]);
+    }
+
+    fn key_tap(&self, key: KeybdKey) {
+        self.record([InputEvent::KeyDown(key), InputEvent::KeyUp(key)]);
+    }
+
+    fn key_chord(&self, keys: &[KeybdKey]) {
+        let presses = keys.iter().map(|key| InputEvent::KeyDown(*key));
+        let releases = keys.iter().rev().map(|key| InputEvent::KeyUp(*key));
+        self.record(presses.chain(releases));
+    }
+
+    fn key_is_pressed(&self, key: KeybdKey) -> bool {
+        self.lock().keys_down.contains(&key)
+    }
+
+    fn key_is_toggled(&self, key: KeybdKey) -> bool {
+        self.lock().keys_toggled.contains(&key)
+    }
+
+    fn mouse_press(&self, button: MouseButton) {
+        self.record([InputEvent::MouseDown(button)]);
+    }
+
+    fn mouse_release(&self, button: MouseButton) {
+        self.record([InputEvent::MouseUp(button)]);
+    }
+
+    fn mouse_tap(&self, button: MouseButton) {
+        self.record([InputEvent::MouseDown(button), InputEvent::MouseUp(button)]);
+    }
+
+    fn mouse_is_pressed(&self, button: MouseButton) -> bool {
+        self.lock().buttons_down.contains(&button)
+    }
+
+    fn cursor_pos(&self) -> (i32, i32) {
+        self.lock().cursor
+    }
+
+    fn move_abs(&self, x: i32, y: i32) {
+        self.record([InputEvent::MoveAbs(x, y)]);
+    }
+
+    fn scroll_ver(&self, delta: i32) {
+        self.record([InputEvent::WheelVer(delta)]);
+    }
+
+    fn scroll_hor(&self, delta: i32) {
+        self.record([InputEvent::WheelHor(delta)]);
+    }
+
+    fn send_sequence(&self, input: &str) {
+        self.record([InputEvent::Text(input.to_string())]);
+    }
+
+    fn get_clipboard_string(&self) -> Option<String> {
+        self.lock().clipboard.clone()
+    }
+
+    fn primary_monitor_size(&self) -> anyhow::Result<(i32, i32)> {
+        Ok(self.lock().monitor_size)
+    }
+
+    fn keyboard_layout(&self) -> anyhow::Result<Option<String>> {
+        Ok(self.lock().layout_id.clone())
+    }
+
+    fn set_keyboard_layout(&self, layout: &'static KeyboardLayout) {
+        self.lock().selected_layout = Some(layout);
+    }
+
+    fn listen_once(
+        &self,
+        hotkey: Hotkey,
+        callback: Box<dyn FnOnce() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle> {
+        let (handle, receiver) = self.listen(hotkey);
+        thread::Builder::new()
+            .name(format!("{} SingleListener", &hotkey))
+            .spawn(move || {
+                if receiver.recv().is_ok() {
+                    callback();
+                }
+            })?;
+        Ok(handle)
+    }
+
+    fn detached_hotkey(
+        &self,
+        hotkey: Hotkey,
+        callback: Box<dyn Fn() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle> {
+        let (handle, receiver) = self.listen(hotkey);
+        thread::Builder::new()
+            .name(format!("{} DetachedHotKey", &hotkey))
+            .spawn(move || {
+                while receiver.recv().is_ok() {
+                    tracing::info!("HotKey bound to '{}' pressed", hotkey);
+                    callback();
+                }
+            })?;
+        Ok(handle)
+    }
+
+    // nothing reaches other applications either way, `swallow` is irrelevant
+    fn hook_mouse(
+        &self,
+        button: MouseButton,
+        _swallow: bool,
+        callback: Box<dyn Fn() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle> {
+        let (handle, receiver) = self.listen(button);
+        thread::Builder::new()
+            .name(format!("{:?} MouseHook", button))
+            .spawn(move || {
+                while receiver.recv().is_ok() {
+                    tracing::info!("Hooked mouse button '{:?}' pressed", button);
+                    callback();
+                }
+            })?;
+        Ok(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeybdKey::*;
+    use crate::Modifiers;
+
+    #[test]
+    fn tap_records_down_up() {
+        let backend = RecordingBackend::new();
+        backend.key_tap(AKey);
+        backend.move_abs(10, 20);
+        assert_eq!(
+            backend.inputs(),
+            vec![
+                InputEvent::KeyDown(AKey),
+                InputEvent::KeyUp(AKey),
+                InputEvent::MoveAbs(10, 20)
+            ]
+        );
+        assert_eq!(backend.cursor_pos(), (10, 20));
+        assert!(!backend.key_is_pressed(AKey));
+    }
+
+    #[test]
+    fn chord_releases_in_reverse() {
+        let backend = RecordingBackend::new();
+        backend.key_chord(&[LControlKey, LShiftKey, SKey]);
+        assert_eq!(
+            backend.inputs(),
+            vec![
+                InputEvent::KeyDown(LControlKey),
+                InputEvent::KeyDown(LShiftKey),
+                InputEvent::KeyDown(SKey),
+                InputEvent::KeyUp(SKey),
+                InputEvent::KeyUp(LShiftKey),
+                InputEvent::KeyUp(LControlKey),
+            ]
+        );
+        assert!(!backend.key_is_pressed(LControlKey));
+    }
+
+    #[test]
+    fn simulated_hotkey() {
+        let backend = RecordingBackend::new();
+        let (sender, receiver) = mpsc::channel();
+        let _handle = backend
+            .listen_once(EscapeKey.into(), Box::new(move || sender.send(()).unwrap()))
+            .unwrap();
+        assert!(backend.is_listening(EscapeKey));
+        assert_eq!(backend.trigger_hotkey(EscapeKey), 1);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+    }
+
+    #[test]
+    fn hotkey_requires_its_modifiers() {
+        let backend = RecordingBackend::new();
+        let ctrl_alt = Modifiers::from_key(LControlKey)
+            .unwrap()
+            .union(Modifiers::from_key(RAltKey).unwrap());
+        let hotkey = Hotkey::new(ctrl_alt, Numrow1Key);
+        let _handle = backend.detached_hotkey(hotkey, Box::new(|| ())).unwrap();
+        assert!(!backend.is_listening(Numrow1Key));
+        assert_eq!(backend.trigger_hotkey(Numrow1Key), 0);
+        assert_eq!(backend.trigger_hotkey(hotkey), 1);
+        assert_eq!(hotkey.to_string(), "ctrl+alt+Numrow1Key");
+    }
+
+    #[test]
+    fn dropped_handle_unregisters() {
+        let backend = RecordingBackend::new();
+        let first = backend
+            .detached_hotkey(F1Key.into(), Box::new(|| ()))
+            .unwrap();
+        let second = backend
+            .detached_hotkey(F1Key.into(), Box::new(|| ()))
+            .unwrap();
+        assert_ne!(first.id(), second.id());
+        drop(first);
+        assert_eq!(backend.trigger_hotkey(F1Key), 1);
+        second.cancel();
+        assert!(!backend.is_listening(F1Key));
+        assert_eq!(backend.trigger_hotkey(F1Key), 0);
+    }
+
+    #[test]
+    fn hooked_mouse_button() {
+        let backend = RecordingBackend::new();
+        let (sender, receiver) = mpsc::channel();
+        let handle = backend
+            .hook_mouse(
+                MouseButton::X1Button,
+                true,
+                Box::new(move || sender.send(()).unwrap()),
+            )
+            .unwrap();
+        assert!(backend.is_listening(MouseButton::X1Button));
+        assert_eq!(backend.trigger_hotkey(MouseButton::X2Button), 0);
+        assert_eq!(backend.trigger_hotkey(MouseButton::X1Button), 1);
+        receiver.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!(handle.trigger().to_string(), "X1Button");
+        drop(handle);
+        assert!(!backend.is_listening(MouseButton::X1Button));
+    }
+}
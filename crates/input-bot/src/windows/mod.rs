@@ -1,177 +1,97 @@
-use crate::public::*;
+use crate::{Hotkey, HotkeyHandle, HotkeyId, InputBackend, Modifiers, Trigger, public::*};
+use anyhow::Context;
 use std::{
-    ffi::c_int,
+    cell::RefCell,
+    ffi::{CStr, c_int},
     mem::{MaybeUninit, size_of},
+    sync::mpsc,
     thread,
 };
 use windows::Win32::UI::{
+    HiDpi::{DPI_AWARENESS_CONTEXT_SYSTEM_AWARE, SetThreadDpiAwarenessContext},
     Input::KeyboardAndMouse::{
-        GetAsyncKeyState, GetKeyState, HOT_KEY_MODIFIERS, INPUT, INPUT_0, INPUT_KEYBOARD,
-        INPUT_MOUSE, KEYBD_EVENT_FLAGS, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_SCANCODE,
-        KEYEVENTF_UNICODE, MAP_VIRTUAL_KEY_TYPE, MOUSE_EVENT_FLAGS, MOUSEEVENTF_HWHEEL,
-        MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP,
-        MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN,
-        MOUSEEVENTF_XUP, MOUSEINPUT, MapVirtualKeyW, RegisterHotKey, SendInput, UnregisterHotKey,
-        VIRTUAL_KEY, VK_PACKET,
+        GetAsyncKeyState, GetKeyState, GetKeyboardLayoutNameA, HOT_KEY_MODIFIERS, INPUT, INPUT_0,
+        INPUT_KEYBOARD, INPUT_MOUSE, KEYBD_EVENT_FLAGS, KEYBDINPUT, KEYEVENTF_KEYUP,
+        KEYEVENTF_SCANCODE, KEYEVENTF_UNICODE, MAP_VIRTUAL_KEY_TYPE, MOD_ALT, MOD_CONTROL,
+        MOD_SHIFT, MOD_WIN, MOUSE_EVENT_FLAGS, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_LEFTDOWN,
+        MOUSEEVENTF_LEFTUP, MOUSEEVENTF_MIDDLEDOWN, MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_RIGHTDOWN,
+        MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_WHEEL, MOUSEEVENTF_XDOWN, MOUSEEVENTF_XUP, MOUSEINPUT,
+        MapVirtualKeyW, RegisterHotKey, SendInput, UnregisterHotKey, VIRTUAL_KEY, VK_PACKET,
+    },
+    WindowsAndMessaging::{
+        CallNextHookEx, GetCursorPos, GetMessageW, GetSystemMetrics, HC_ACTION, LLMHF_INJECTED,
+        MSG, MSLLHOOKSTRUCT, PM_NOREMOVE, PeekMessageW, PostThreadMessageW, SYSTEM_METRICS_INDEX,
+        SetCursorPos, SetWindowsHookExW, UnhookWindowsHookEx, WH_MOUSE_LL, WM_HOTKEY,
+        WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_QUIT, WM_RBUTTONDOWN,
+        WM_RBUTTONUP, WM_XBUTTONDOWN, WM_XBUTTONUP,
     },
-    WindowsAndMessaging::{GetCursorPos, GetMessageW, MSG, SetCursorPos},
 };
 use windows::Win32::{
-    Foundation::HGLOBAL,
+    Foundation::{HGLOBAL, LPARAM, LRESULT, WPARAM},
     System::{
         DataExchange::{CloseClipboard, GetClipboardData, GetClipboardOwner, OpenClipboard},
         Memory::GlobalSize,
+        Threading::GetCurrentThreadId,
     },
 };
 
-mod inputs;
+/// Win32 implementation of `InputBackend`, keys are sent as scan codes, hotkeys
+/// are registered through `RegisterHotKey` and mouse buttons observed with a low-level hook
+pub struct WindowsBackend;
 
-/// get_clipboard attempts to collect the clipboard's content to a string
-/// returns None in case it fails
-pub fn get_clipboard_string() -> Option<String> {
-    unsafe {
-        use std::os::raw::c_void;
-        OpenClipboard(
-            GetClipboardOwner()
-                .map_err(|err| tracing::warn!("Failed to get clipboard owner: {}", err))
-                .ok(),
-        )
-        .map_err(|err| tracing::warn!("Failed to open clipboard: {}", err))
-        .ok()?;
-        // CF_TEXT = 1
-        // not using GlobalLock as we are not writing anything
-        let clipboard_data: *mut c_void = GetClipboardData(1)
-            .map_err(|err| tracing::warn!("Failed to read clipboard: {}", err))
-            .ok()?
-            .0 as *mut c_void;
-        let size: usize = GlobalSize(HGLOBAL(clipboard_data));
-        let ptr: *const u8 = clipboard_data as *const u8;
-        let string: String =
-            String::from_utf8_lossy(std::slice::from_raw_parts(ptr, size)).to_string();
-        let _ = CloseClipboard();
-        Some(string.to_string())
+impl WindowsBackend {
+    pub fn new() -> Self {
+        // fixes issues with scale when querying the monitor size and moving the cursor
+        unsafe { SetThreadDpiAwarenessContext(DPI_AWARENESS_CONTEXT_SYSTEM_AWARE) };
+        Self
     }
 }
 
-pub fn send_sequence(input: &str) {
-    let inputs: Vec<INPUT> = input
-        .encode_utf16()
-        .map(|code| {
-            let keybd_input = KEYBDINPUT {
-                wVk: VK_PACKET,
-                wScan: code,
-                dwFlags: KEYEVENTF_UNICODE,
-                time: 0,
-                dwExtraInfo: 0,
-            };
-            INPUT {
-                r#type: INPUT_KEYBOARD,
-                Anonymous: INPUT_0 { ki: keybd_input },
-            }
-        })
-        .collect();
-
-    unsafe {
-        SendInput(&inputs, std::mem::size_of::<INPUT>() as std::ffi::c_int);
+impl Default for WindowsBackend {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl KeybdKey {
-    /// Returns true if a given `KeybdKey` is currently pressed (in the down position).
-    pub fn is_pressed(self) -> bool {
-        (unsafe { GetAsyncKeyState(u64::from(self) as i32) } >> 15) != 0
+impl InputBackend for WindowsBackend {
+    fn name(&self) -> &'static str {
+        "windows"
     }
 
-    /// Presses a given `KeybdKey`. Note: this means the key will remain in the down
-    /// position. You must manually call release to create a full 'press'.
-    pub fn press(self) {
-        send_keybd_input(KEYEVENTF_SCANCODE, self);
+    fn key_press(&self, key: KeybdKey) {
+        send_keybd_input(KEYEVENTF_SCANCODE, key);
     }
 
-    /// Releases a given `KeybdKey`. This means the key would be in the up position.
-    pub fn release(self) {
-        send_keybd_input(KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP, self);
+    fn key_release(&self, key: KeybdKey) {
+        send_keybd_input(KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP, key);
     }
 
-    pub fn tap(self) {
+    fn key_tap(&self, key: KeybdKey) {
         send_keybd_inputs(vec![
-            (KEYEVENTF_SCANCODE, self),
-            (KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP, self),
+            (KEYEVENTF_SCANCODE, key),
+            (KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP, key),
         ])
     }
 
-    /// Returns true if a keyboard key which supports toggling (ScrollLock, NumLock,
-    /// CapsLock) is on.
-    pub fn is_toggled(self) -> bool {
-        unsafe { GetKeyState(u64::from(self) as i32) & 15 != 0 }
+    fn key_chord(&self, keys: &[KeybdKey]) {
+        let presses = keys.iter().map(|key| (KEYEVENTF_SCANCODE, *key));
+        let releases = keys
+            .iter()
+            .rev()
+            .map(|key| (KEYEVENTF_SCANCODE | KEYEVENTF_KEYUP, *key));
+        send_keybd_inputs(presses.chain(releases).collect())
     }
 
-    pub fn listen_once<F: FnOnce() + Send + 'static>(
-        self,
-        callback: F,
-    ) -> std::io::Result<thread::JoinHandle<()>> {
-        thread::Builder::new()
-            .name(format!("{:?} SingleListener", &self))
-            .spawn(move || {
-                if let Err(err) =
-                    unsafe { RegisterHotKey(None, 0, HOT_KEY_MODIFIERS(0), u64::from(self) as u32) }
-                {
-                    tracing::error!("Failed to bind HotKey, '{}'", err);
-                    callback();
-                } else {
-                    let mut msg: MSG = unsafe { MaybeUninit::zeroed().assume_init() };
-                    unsafe { let _ = GetMessageW(&mut msg, None, 0, 0); }
-                    callback();
-                }
-            })
+    fn key_is_pressed(&self, key: KeybdKey) -> bool {
+        (unsafe { GetAsyncKeyState(u64::from(key) as i32) } >> 15) != 0
     }
 
-    pub fn await_in_place(self) -> anyhow::Result<()> {
-        if let Err(err) =
-            unsafe { RegisterHotKey(None, 0, HOT_KEY_MODIFIERS(0), u64::from(self) as u32) }
-        {
-            tracing::error!("Failed to bind HotKey, '{}'", err);
-            Err(anyhow::anyhow!("Failed to register HotKey"))?;
-        }
-        let mut msg: MSG = unsafe { MaybeUninit::zeroed().assume_init() };
-        unsafe {
-            let _ = GetMessageW(&mut msg, None, 0, 0);
-            UnregisterHotKey(None, 0)?;
-        }
-        Ok(())
+    fn key_is_toggled(&self, key: KeybdKey) -> bool {
+        unsafe { GetKeyState(u64::from(key) as i32) & 15 != 0 }
     }
 
-    pub fn detached_hotkey<F: Fn() + Send + 'static>(self, callback: F) -> std::io::Result<()> {
-        thread::Builder::new()
-            .name(format!("{:?} DetachedHotKey", &self))
-            .spawn(move || {
-                if let Err(err) =
-                    unsafe { RegisterHotKey(None, 0, HOT_KEY_MODIFIERS(0), u64::from(self) as u32) }
-                {
-                    tracing::error!("Failed to bind HotKey, '{}'", err);
-                } else {
-                    loop {
-                        let mut msg: MSG = unsafe { MaybeUninit::zeroed().assume_init() };
-                        unsafe { let _ = GetMessageW(&mut msg, None, 0, 0); }
-                        tracing::info!("HotKey bound to '{:?}' pressed", self);
-                        callback();
-                    }
-                }
-            })?;
-        Ok(())
-    }
-}
-
-impl MouseButton {
-    /// Returns true if a given `MouseButton` is currently pressed (in the down position).
-    pub fn is_pressed(self) -> bool {
-        (unsafe { GetAsyncKeyState(u32::from(self) as i32) } >> 15) != 0
-    }
-
-    /// Presses a given `MouseButton`. Note: this means the button will remain in the down
-    /// position. You must manually call release to create a full 'click'.
-    pub fn press(self) {
-        match self {
+    fn mouse_press(&self, button: MouseButton) {
+        match button {
             MouseButton::LeftButton => send_mouse_input(MOUSEEVENTF_LEFTDOWN, 0, 0, 0),
             MouseButton::RightButton => send_mouse_input(MOUSEEVENTF_RIGHTDOWN, 0, 0, 0),
             MouseButton::MiddleButton => send_mouse_input(MOUSEEVENTF_MIDDLEDOWN, 0, 0, 0),
@@ -181,9 +101,8 @@ impl MouseButton {
         }
     }
 
-    /// Releases a given `MouseButton`. This means the button would be in the up position.
-    pub fn release(self) {
-        match self {
+    fn mouse_release(&self, button: MouseButton) {
+        match button {
             MouseButton::LeftButton => send_mouse_input(MOUSEEVENTF_LEFTUP, 0, 0, 0),
             MouseButton::RightButton => send_mouse_input(MOUSEEVENTF_RIGHTUP, 0, 0, 0),
             MouseButton::MiddleButton => send_mouse_input(MOUSEEVENTF_MIDDLEUP, 0, 0, 0),
@@ -193,15 +112,15 @@ impl MouseButton {
         }
     }
 
-    pub fn tap(self) {
-        match self {
+    fn mouse_tap(&self, button: MouseButton) {
+        match button {
             MouseButton::LeftButton => send_mouse_inputs(vec![
                 (MOUSEEVENTF_LEFTDOWN, 0, 0, 0),
                 (MOUSEEVENTF_LEFTUP, 0, 0, 0),
             ]),
             MouseButton::RightButton => send_mouse_inputs(vec![
                 (MOUSEEVENTF_RIGHTDOWN, 0, 0, 0),
-                (MOUSEEVENTF_RIGHTDOWN, 0, 0, 0),
+                (MOUSEEVENTF_RIGHTUP, 0, 0, 0),
             ]),
             MouseButton::MiddleButton => send_mouse_inputs(vec![
                 (MOUSEEVENTF_MIDDLEDOWN, 0, 0, 0),
@@ -218,54 +137,335 @@ impl MouseButton {
             _ => {}
         }
     }
-}
 
-impl MouseCursor {
-    pub fn pos() -> (i32, i32) {
+    fn mouse_is_pressed(&self, button: MouseButton) -> bool {
+        (unsafe { GetAsyncKeyState(u32::from(button) as i32) } >> 15) != 0
+    }
+
+    fn cursor_pos(&self) -> (i32, i32) {
         let mut point = MaybeUninit::uninit();
         unsafe { GetCursorPos(point.as_mut_ptr()).unwrap() };
         let point = unsafe { point.assume_init() };
         (point.x, point.y)
     }
 
-    /// Moves the mouse relative to its current position by a given amount of pixels.
-    pub fn move_rel(dx: i32, dy: i32) {
-        let (x, y) = Self::pos();
-        Self::move_abs(x + dx, y + dy);
+    fn move_abs(&self, x: i32, y: i32) {
+        unsafe {
+            SetCursorPos(x, y).unwrap();
+        }
+    }
+
+    // see https://github.com/microsoft/win32metadata/issues/1865#issuecomment-1977365435
+    fn scroll_ver(&self, delta: i32) {
+        send_mouse_input(MOUSEEVENTF_WHEEL, delta as u32, 0, 0);
+    }
+
+    fn scroll_hor(&self, delta: i32) {
+        send_mouse_input(MOUSEEVENTF_HWHEEL, delta as u32, 0, 0);
+    }
+
+    fn send_sequence(&self, input: &str) {
+        let inputs: Vec<INPUT> = input
+            .encode_utf16()
+            .map(|code| {
+                let keybd_input = KEYBDINPUT {
+                    wVk: VK_PACKET,
+                    wScan: code,
+                    dwFlags: KEYEVENTF_UNICODE,
+                    time: 0,
+                    dwExtraInfo: 0,
+                };
+                INPUT {
+                    r#type: INPUT_KEYBOARD,
+                    Anonymous: INPUT_0 { ki: keybd_input },
+                }
+            })
+            .collect();
+
+        unsafe {
+            SendInput(&inputs, std::mem::size_of::<INPUT>() as std::ffi::c_int);
+        }
     }
 
-    /// Moves the mouse to a given position based on absolute coordinates. The top left
-    /// corner of the screen is (0, 0).
-    pub fn move_abs(x: i32, y: i32) {
+    fn get_clipboard_string(&self) -> Option<String> {
         unsafe {
-            SetCursorPos(x, y).unwrap();
+            use std::os::raw::c_void;
+            OpenClipboard(
+                GetClipboardOwner()
+                    .map_err(|err| tracing::warn!("Failed to get clipboard owner: {}", err))
+                    .ok(),
+            )
+            .map_err(|err| tracing::warn!("Failed to open clipboard: {}", err))
+            .ok()?;
+            // CF_TEXT = 1
+            // not using GlobalLock as we are not writing anything
+            let clipboard_data: *mut c_void = GetClipboardData(1)
+                .map_err(|err| tracing::warn!("Failed to read clipboard: {}", err))
+                .ok()?
+                .0 as *mut c_void;
+            let size: usize = GlobalSize(HGLOBAL(clipboard_data));
+            let ptr: *const u8 = clipboard_data as *const u8;
+            let string: String =
+                String::from_utf8_lossy(std::slice::from_raw_parts(ptr, size)).to_string();
+            let _ = CloseClipboard();
+            Some(string.to_string())
         }
     }
+
+    fn primary_monitor_size(&self) -> anyhow::Result<(i32, i32)> {
+        let width = unsafe { GetSystemMetrics(SYSTEM_METRICS_INDEX(0)) };
+        let height = unsafe { GetSystemMetrics(SYSTEM_METRICS_INDEX(1)) };
+        Ok((width, height))
+    }
+
+    fn keyboard_layout(&self) -> anyhow::Result<Option<String>> {
+        let mut pwszklid = [0_u8; 9];
+        unsafe {
+            GetKeyboardLayoutNameA(&mut pwszklid).context("Failed to get keyboard layout")?;
+        }
+        let keyboard_layout = CStr::from_bytes_with_nul(&pwszklid)?.to_str()?;
+        Ok(Some(keyboard_layout.to_string()))
+    }
+
+    fn listen_once(
+        &self,
+        hotkey: Hotkey,
+        callback: Box<dyn FnOnce() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle> {
+        spawn_hotkey_thread(
+            format!("{} SingleListener", &hotkey),
+            hotkey,
+            move |registered| match registered {
+                Ok(id) => {
+                    if wait_hotkey(id) {
+                        callback()
+                    } else {
+                        tracing::debug!("HotKey '{}' cancelled", hotkey)
+                    }
+                }
+                Err(err) => {
+                    tracing::error!("Failed to bind HotKey, '{}'", err);
+                    callback();
+                }
+            },
+        )
+    }
+
+    fn detached_hotkey(
+        &self,
+        hotkey: Hotkey,
+        callback: Box<dyn Fn() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle> {
+        spawn_hotkey_thread(
+            format!("{} DetachedHotKey", &hotkey),
+            hotkey,
+            move |registered| match registered {
+                Ok(id) => {
+                    while wait_hotkey(id) {
+                        tracing::info!("HotKey bound to '{}' pressed", hotkey);
+                        callback();
+                    }
+                    tracing::debug!("HotKey '{}' cancelled", hotkey);
+                }
+                Err(err) => tracing::error!("Failed to bind HotKey, '{}'", err),
+            },
+        )
+    }
+
+    fn hook_mouse(
+        &self,
+        button: MouseButton,
+        swallow: bool,
+        callback: Box<dyn Fn() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle> {
+        let (presses, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name(format!("{:?} MouseHookCallback", button))
+            .spawn(move || {
+                while receiver.recv().is_ok() {
+                    tracing::info!("Hooked mouse button '{:?}' pressed", button);
+                    callback();
+                }
+            })?;
+        // the hook is installed by the message thread, which reports whether it succeeded
+        let (installed, installation) = mpsc::channel();
+        let handle = spawn_message_thread(
+            format!("{:?} MouseHook", button),
+            button.into(),
+            move |_| {
+                MOUSE_HOOK.set(Some(MouseHook {
+                    button,
+                    swallow,
+                    presses,
+                }));
+                let hook =
+                    match unsafe { SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_hook_proc), None, 0) }
+                    {
+                        Ok(hook) => hook,
+                        Err(err) => {
+                            // stops the callback thread
+                            MOUSE_HOOK.set(None);
+                            let _ = installed.send(Err(err));
+                            return;
+                        }
+                    };
+                let _ = installed.send(Ok(()));
+                // the hook is only called while the thread waits for messages
+                let mut msg: MSG = unsafe { MaybeUninit::zeroed().assume_init() };
+                while unsafe { GetMessageW(&mut msg, None, 0, 0) }.0 > 0 {}
+                unsafe {
+                    let _ = UnhookWindowsHookEx(hook);
+                }
+                // stops the callback thread
+                MOUSE_HOOK.set(None);
+                tracing::debug!("Mouse hook '{:?}' removed", button);
+            },
+        )?;
+        installation
+            .recv()
+            .map_err(std::io::Error::other)?
+            .map_err(|err| {
+                std::io::Error::other(format!("Failed to hook '{:?}', '{}'", button, err))
+            })?;
+        Ok(handle)
+    }
 }
 
-impl MouseWheel {
-    /// Scrolls the mouse wheel vertically by a given amount of "wheel clicks".
-    pub fn scroll_ver(dwheel: u32) {
-        send_mouse_input(MOUSEEVENTF_WHEEL, dwheel * 120, 0, 0);
+fn register_hotkey(id: HotkeyId, hotkey: Hotkey) -> windows::core::Result<()> {
+    unsafe {
+        RegisterHotKey(
+            None,
+            id.0,
+            hot_key_modifiers(hotkey.modifiers),
+            u64::from(hotkey.key) as u32,
+        )
     }
+}
+
+/// Registers the hotkey with a unique id on a new thread, as hotkey messages are posted to
+/// the thread that registered them. The hotkey is unregistered once the thread quits
+fn spawn_hotkey_thread(
+    name: String,
+    hotkey: Hotkey,
+    body: impl FnOnce(windows::core::Result<HotkeyId>) + Send + 'static,
+) -> std::io::Result<HotkeyHandle> {
+    spawn_message_thread(name, hotkey.into(), move |id| {
+        let registered = register_hotkey(id, hotkey);
+        let unregister = registered.is_ok();
+        body(registered.map(|_| id));
+        if unregister {
+            unsafe {
+                let _ = UnregisterHotKey(None, id.0);
+            }
+        }
+    })
+}
+
+/// Spawns a thread with its own message queue, the returned handle stops it by posting
+/// `WM_QUIT` to that queue
+fn spawn_message_thread(
+    name: String,
+    trigger: Trigger,
+    body: impl FnOnce(HotkeyId) + Send + 'static,
+) -> std::io::Result<HotkeyHandle> {
+    let id = HotkeyId::next();
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new().name(name).spawn(move || {
+        let mut msg: MSG = unsafe { MaybeUninit::zeroed().assume_init() };
+        // creates the message queue of the thread, so that WM_QUIT can be posted to it
+        unsafe {
+            let _ = PeekMessageW(&mut msg, None, 0, 0, PM_NOREMOVE);
+        }
+        let _ = sender.send(unsafe { GetCurrentThreadId() });
+        body(id);
+    })?;
+    let thread_id = receiver.recv().map_err(std::io::Error::other)?;
+    Ok(HotkeyHandle::new(id, trigger, move || unsafe {
+        let _ = PostThreadMessageW(thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+    }))
+}
+
+struct MouseHook {
+    button: MouseButton,
+    swallow: bool,
+    // presses are handed over to another thread, as low-level hooks have to return quickly
+    presses: mpsc::Sender<()>,
+}
+
+thread_local! {
+    // low-level hooks are called on the thread that installed them, which installs one at most
+    static MOUSE_HOOK: RefCell<Option<MouseHook>> = const { RefCell::new(None) };
+}
 
-    /// Scrolls the mouse wheel horizontally by a given amount of "wheel clicks".
-    pub fn scroll_hor(dwheel: u32) {
-        send_mouse_input(MOUSEEVENTF_HWHEEL, dwheel * 120, 0, 0);
+unsafe extern "system" fn mouse_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION as i32 {
+        let info = unsafe { &*(lparam.0 as *const MSLLHOOKSTRUCT) };
+        // inputs sent by the script itself are left alone
+        let injected = info.flags & LLMHF_INJECTED != 0;
+        let swallowed = MOUSE_HOOK.with_borrow(|hook| match (hook, hooked_button(wparam, info)) {
+            (Some(hook), Some((button, pressed))) if button == hook.button && !injected => {
+                if pressed {
+                    let _ = hook.presses.send(());
+                }
+                hook.swallow
+            }
+            _ => false,
+        });
+        if swallowed {
+            return LRESULT(1);
+        }
     }
+    unsafe { CallNextHookEx(None, code, wparam, lparam) }
+}
 
-    /// Scrolls the mouse wheel vertically by a given amount.
-    /// Cast a i32 to a u32 beforehand, see https://github.com/microsoft/win32metadata/issues/1865#issuecomment-1977365435
-    pub fn scroll_ver_unscaled(dwheel: u32) {
-        send_mouse_input(MOUSEEVENTF_WHEEL, dwheel, 0, 0);
+/// Button of a low-level mouse message and whether it was pressed or released
+fn hooked_button(wparam: WPARAM, info: &MSLLHOOKSTRUCT) -> Option<(MouseButton, bool)> {
+    // the high word of mouseData holds XBUTTON1 (1) or XBUTTON2 (2)
+    let xbutton = if info.mouseData >> 16 == 1 {
+        MouseButton::X1Button
+    } else {
+        MouseButton::X2Button
+    };
+    match wparam.0 as u32 {
+        WM_LBUTTONDOWN => Some((MouseButton::LeftButton, true)),
+        WM_LBUTTONUP => Some((MouseButton::LeftButton, false)),
+        WM_RBUTTONDOWN => Some((MouseButton::RightButton, true)),
+        WM_RBUTTONUP => Some((MouseButton::RightButton, false)),
+        WM_MBUTTONDOWN => Some((MouseButton::MiddleButton, true)),
+        WM_MBUTTONUP => Some((MouseButton::MiddleButton, false)),
+        WM_XBUTTONDOWN => Some((xbutton, true)),
+        WM_XBUTTONUP => Some((xbutton, false)),
+        _ => None,
     }
+}
 
-    /// Scrolls the mouse wheel horizontally by a given amount.
-    pub fn scroll_hor_unscaled(dwheel: u32) {
-        send_mouse_input(MOUSEEVENTF_HWHEEL, dwheel, 0, 0);
+/// Blocks until the hotkey registered with `id` is pressed, returns false once the
+/// thread is asked to quit
+fn wait_hotkey(id: HotkeyId) -> bool {
+    loop {
+        let mut msg: MSG = unsafe { MaybeUninit::zeroed().assume_init() };
+        let ret = unsafe { GetMessageW(&mut msg, None, 0, 0) };
+        if ret.0 == 0 || ret.0 == -1 {
+            return false;
+        }
+        if msg.message == WM_HOTKEY && msg.wParam.0 == id.0 as usize {
+            return true;
+        }
     }
 }
 
+fn hot_key_modifiers(modifiers: Modifiers) -> HOT_KEY_MODIFIERS {
+    [
+        (modifiers.ctrl, MOD_CONTROL),
+        (modifiers.alt, MOD_ALT),
+        (modifiers.shift, MOD_SHIFT),
+        (modifiers.win, MOD_WIN),
+    ]
+    .into_iter()
+    .filter(|(held, _)| *held)
+    .fold(HOT_KEY_MODIFIERS(0), |mask, (_, modifier)| mask | modifier)
+}
+
 fn send_mouse_input(flags: MOUSE_EVENT_FLAGS, data: u32, dx: i32, dy: i32) {
     let mouse = MOUSEINPUT {
         dx,
@@ -0,0 +1,158 @@
+use crate::{
+    layout::KeyboardLayout,
+    public::{
+        KeybdKey::{self, *},
+        MouseButton::{self, *},
+    },
+};
+
+// https://gitlab.freedesktop.org/xorg/proto/xorgproto/-/blob/master/include/X11/keysymdef.h
+// https://gitlab.freedesktop.org/xorg/proto/xorgproto/-/blob/master/include/X11/XF86keysym.h
+// keys whose character depends on the layout are looked up in it first,
+// the OEM keys have no keysym outside of a layout
+pub(super) fn keysym(key: KeybdKey, layout: &KeyboardLayout) -> Option<u32> {
+    if let Some(keysym) = layout.keysym(key) {
+        return Some(keysym);
+    }
+    Some(match key {
+        BackspaceKey => 0xFF08,
+        TabKey => 0xFF09,
+        EnterKey => 0xFF0D,
+        EscapeKey => 0xFF1B,
+        SpaceKey => 0x0020,
+        PageUpKey => 0xFF55,
+        PageDownKey => 0xFF56,
+        EndKey => 0xFF57,
+        HomeKey => 0xFF50,
+        LeftKey => 0xFF51,
+        UpKey => 0xFF52,
+        RightKey => 0xFF53,
+        DownKey => 0xFF54,
+        InsertKey => 0xFF63,
+        DeleteKey => 0xFFFF,
+        Numrow0Key => 0x0030,
+        Numrow1Key => 0x0031,
+        Numrow2Key => 0x0032,
+        Numrow3Key => 0x0033,
+        Numrow4Key => 0x0034,
+        Numrow5Key => 0x0035,
+        Numrow6Key => 0x0036,
+        Numrow7Key => 0x0037,
+        Numrow8Key => 0x0038,
+        Numrow9Key => 0x0039,
+        AKey => 0x0061,
+        BKey => 0x0062,
+        CKey => 0x0063,
+        DKey => 0x0064,
+        EKey => 0x0065,
+        FKey => 0x0066,
+        GKey => 0x0067,
+        HKey => 0x0068,
+        IKey => 0x0069,
+        JKey => 0x006A,
+        KKey => 0x006B,
+        LKey => 0x006C,
+        MKey => 0x006D,
+        NKey => 0x006E,
+        OKey => 0x006F,
+        PKey => 0x0070,
+        QKey => 0x0071,
+        RKey => 0x0072,
+        SKey => 0x0073,
+        TKey => 0x0074,
+        UKey => 0x0075,
+        VKey => 0x0076,
+        WKey => 0x0077,
+        XKey => 0x0078,
+        YKey => 0x0079,
+        ZKey => 0x007A,
+        LSuper => 0xFFEB,
+        RSuper => 0xFFEC,
+        Numpad0Key => 0xFFB0,
+        Numpad1Key => 0xFFB1,
+        Numpad2Key => 0xFFB2,
+        Numpad3Key => 0xFFB3,
+        Numpad4Key => 0xFFB4,
+        Numpad5Key => 0xFFB5,
+        Numpad6Key => 0xFFB6,
+        Numpad7Key => 0xFFB7,
+        Numpad8Key => 0xFFB8,
+        Numpad9Key => 0xFFB9,
+        F1Key => 0xFFBE,
+        F2Key => 0xFFBF,
+        F3Key => 0xFFC0,
+        F4Key => 0xFFC1,
+        F5Key => 0xFFC2,
+        F6Key => 0xFFC3,
+        F7Key => 0xFFC4,
+        F8Key => 0xFFC5,
+        F9Key => 0xFFC6,
+        F10Key => 0xFFC7,
+        F11Key => 0xFFC8,
+        F12Key => 0xFFC9,
+        F13Key => 0xFFCA,
+        F14Key => 0xFFCB,
+        F15Key => 0xFFCC,
+        F16Key => 0xFFCD,
+        F17Key => 0xFFCE,
+        F18Key => 0xFFCF,
+        F19Key => 0xFFD0,
+        F20Key => 0xFFD1,
+        F21Key => 0xFFD2,
+        F22Key => 0xFFD3,
+        F23Key => 0xFFD4,
+        F24Key => 0xFFD5,
+        NumLockKey => 0xFF7F,
+        ScrollLockKey => 0xFF14,
+        CapsLockKey => 0xFFE5,
+        LShiftKey => 0xFFE1,
+        RShiftKey => 0xFFE2,
+        LControlKey => 0xFFE3,
+        RControlKey => 0xFFE4,
+        LAltKey => 0xFFE9,
+        // AltGr
+        RAltKey => 0xFE03,
+        BrowserBackKey => 0x1008FF26,
+        BrowserForwardKey => 0x1008FF27,
+        BrowserRefreshKey => 0x1008FF29,
+        VolumeMuteKey => 0x1008FF12,
+        VolumeDownKey => 0x1008FF11,
+        VolumeUpKey => 0x1008FF13,
+        MediaNextTrackKey => 0x1008FF17,
+        MediaPrevTrackKey => 0x1008FF16,
+        MediaStopKey => 0x1008FF15,
+        MediaPlayPauseKey => 0x1008FF14,
+
+        CommaKey => 0x002C,
+        PeriodKey => 0x002E,
+        DashKey => 0x002D,
+        OEM1 | OEM2 | OEM3 | OEM4 | OEM5 | OEM6 | OEM7 | OEM8 | OEM102 => return None,
+
+        // interpreted as a keysym
+        OtherKey(code) => code as u32,
+    })
+}
+
+/// keysym of a character that should be typed
+pub(super) fn char_keysym(c: char) -> u32 {
+    match c {
+        '\n' => 0xFF0D,
+        '\t' => 0xFF09,
+        // latin-1 characters map directly onto keysyms
+        '\u{20}'..='\u{7E}' | '\u{A0}'..='\u{FF}' => c as u32,
+        _ => 0x0100_0000 | c as u32,
+    }
+}
+
+pub(super) fn button_code(button: MouseButton) -> u8 {
+    match button {
+        LeftButton => 1,
+        MiddleButton => 2,
+        RightButton => 3,
+        MousewheelUp => 4,
+        MousewheelDown => 5,
+        X1Button => 8,
+        X2Button => 9,
+        OtherButton(code) => code as u8,
+    }
+}
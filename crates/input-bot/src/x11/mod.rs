@@ -0,0 +1,871 @@
+use crate::{
+    Hotkey, HotkeyHandle, HotkeyId, InputBackend, KeyboardLayout, Modifiers, SWISS_FRENCH,
+    public::*,
+};
+use anyhow::Context;
+use keysyms::{button_code, char_keysym, keysym};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use x11rb::{
+    CURRENT_TIME, NONE,
+    connection::{Connection, RequestConnection},
+    protocol::{
+        Event,
+        xproto::{
+            Allow, Atom, AtomEnum, BUTTON_PRESS_EVENT, BUTTON_RELEASE_EVENT, ButtonIndex,
+            ClientMessageEvent, ConnectionExt as _, CreateWindowAux, EventMask, GrabMode,
+            KEY_PRESS_EVENT, KEY_RELEASE_EVENT, KeyButMask, Keycode, Keysym, MOTION_NOTIFY_EVENT,
+            ModMask, Property, Window, WindowClass,
+        },
+        xtest::ConnectionExt as _,
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+};
+
+mod keysyms;
+
+// NumLock and CapsLock are modifiers in X11, a hotkey has to be grabbed for every
+// combination of them to behave like its Windows counterpart
+fn ignored_modifiers() -> [ModMask; 4] {
+    [
+        ModMask::from(0_u16),
+        ModMask::LOCK,
+        ModMask::M2,
+        ModMask::LOCK | ModMask::M2,
+    ]
+}
+
+// Mod1 is Alt and Mod4 is Super on virtually every X server
+fn mod_mask(modifiers: Modifiers) -> ModMask {
+    let masks = [
+        (modifiers.ctrl, ModMask::CONTROL),
+        (modifiers.alt, ModMask::M1),
+        (modifiers.shift, ModMask::SHIFT),
+        (modifiers.win, ModMask::M4),
+    ];
+    masks
+        .into_iter()
+        .filter(|(held, _)| *held)
+        .fold(ModMask::from(0_u16), |mask, (_, modifier)| mask | modifier)
+}
+
+/// X11 implementation of `InputBackend`, inputs are injected through the XTest extension
+/// and hotkeys are registered with `XGrabKey` or `XGrabButton`, each on their own connection
+pub struct X11Backend {
+    conn: RustConnection,
+    root: Window,
+    screen_size: (i32, i32),
+    mapping: Mutex<KeyboardMapping>,
+    // describes the characters typed by the OEM keys, which are found through them
+    layout: Mutex<&'static KeyboardLayout>,
+}
+
+impl X11Backend {
+    pub fn new() -> anyhow::Result<Self> {
+        let (conn, screen_num) =
+            x11rb::connect(None).context("Failed to connect to the X server")?;
+        conn.extension_information(x11rb::protocol::xtest::X11_EXTENSION_NAME)?
+            .context("The X server does not support the XTest extension")?;
+        let screen = &conn.setup().roots[screen_num];
+        let root = screen.root;
+        let screen_size = (
+            screen.width_in_pixels as i32,
+            screen.height_in_pixels as i32,
+        );
+        let mapping = KeyboardMapping::fetch(&conn)?;
+        let layout = match xkb_layout(&conn, root) {
+            Ok(Some(id)) => KeyboardLayout::by_id(&id).unwrap_or_else(|| {
+                tracing::debug!("No profile for the '{}' layout", id);
+                SWISS_FRENCH
+            }),
+            _ => SWISS_FRENCH,
+        };
+        Ok(Self {
+            conn,
+            root,
+            screen_size,
+            mapping: Mutex::new(mapping),
+            layout: Mutex::new(layout),
+        })
+    }
+
+    fn layout(&self) -> &'static KeyboardLayout {
+        *self.layout.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn fake_input(&self, inputs: &[(u8, u8)]) {
+        for (type_, detail) in inputs {
+            if let Err(err) =
+                self.conn
+                    .xtest_fake_input(*type_, *detail, CURRENT_TIME, NONE, 0, 0, 0)
+            {
+                tracing::warn!("Failed to send fake input: {}", err);
+            }
+        }
+        if let Err(err) = self.conn.flush() {
+            tracing::warn!("Failed to flush the X connection: {}", err);
+        }
+    }
+
+    fn keycode(&self, key: KeybdKey) -> Option<Keycode> {
+        let keycode = keysym(key, self.layout())
+            .and_then(|keysym| self.keycode_of(keysym))
+            .map(|(keycode, _)| keycode);
+        if keycode.is_none() {
+            tracing::warn!("No keycode found for '{:?}' in the active layout", key);
+        }
+        keycode
+    }
+
+    /// Looks for the keysym in the cached mapping, refreshing it once on a miss
+    /// as the active layout may have changed
+    fn keycode_of(&self, keysym: Keysym) -> Option<(Keycode, usize)> {
+        let mut mapping = self.mapping.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(found) = mapping.find(keysym) {
+            return Some(found);
+        }
+        match KeyboardMapping::fetch(&self.conn) {
+            Ok(fresh) => *mapping = fresh,
+            Err(err) => tracing::warn!("Failed to refresh the keyboard mapping: {}", err),
+        }
+        mapping.find(keysym)
+    }
+
+    /// Temporarily maps the keysym onto a keycode that is unused by the active layout
+    fn type_unmapped(&self, keysym: Keysym) -> anyhow::Result<()> {
+        let mapping = self.mapping.lock().unwrap_or_else(|err| err.into_inner());
+        let keycode = mapping
+            .unused_keycode()
+            .context("No unused keycode left to type an unmapped character")?;
+        let per_keycode = mapping.keysyms_per_keycode;
+        let mut keysyms = vec![keysym; per_keycode as usize];
+        self.conn
+            .change_keyboard_mapping(1, keycode, per_keycode, &keysyms)?;
+        // make sure the new mapping is in place before tapping it
+        self.conn.sync()?;
+        self.fake_input(&[(KEY_PRESS_EVENT, keycode), (KEY_RELEASE_EVENT, keycode)]);
+        keysyms.fill(0);
+        self.conn
+            .change_keyboard_mapping(1, keycode, per_keycode, &keysyms)?;
+        self.conn.sync()?;
+        Ok(())
+    }
+}
+
+impl InputBackend for X11Backend {
+    fn name(&self) -> &'static str {
+        "x11"
+    }
+
+    fn key_press(&self, key: KeybdKey) {
+        if let Some(keycode) = self.keycode(key) {
+            self.fake_input(&[(KEY_PRESS_EVENT, keycode)]);
+        }
+    }
+
+    fn key_release(&self, key: KeybdKey) {
+        if let Some(keycode) = self.keycode(key) {
+            self.fake_input(&[(KEY_RELEASE_EVENT, keycode)]);
+        }
+    }
+
+    fn key_tap(&self, key: KeybdKey) {
+        if let Some(keycode) = self.keycode(key) {
+            self.fake_input(&[(KEY_PRESS_EVENT, keycode), (KEY_RELEASE_EVENT, keycode)]);
+        }
+    }
+
+    fn key_chord(&self, keys: &[KeybdKey]) {
+        // a key missing from the layout would break the chord, nothing is sent then
+        let Some(keycodes) = keys
+            .iter()
+            .map(|key| self.keycode(*key))
+            .collect::<Option<Vec<Keycode>>>()
+        else {
+            return;
+        };
+        let presses = keycodes.iter().map(|keycode| (KEY_PRESS_EVENT, *keycode));
+        let releases = keycodes
+            .iter()
+            .rev()
+            .map(|keycode| (KEY_RELEASE_EVENT, *keycode));
+        self.fake_input(&presses.chain(releases).collect::<Vec<_>>());
+    }
+
+    fn key_is_pressed(&self, key: KeybdKey) -> bool {
+        let Some(keycode) = self.keycode(key) else {
+            return false;
+        };
+        match self.conn.query_keymap().map(|cookie| cookie.reply()) {
+            Ok(Ok(reply)) => reply.keys[keycode as usize / 8] & (1 << (keycode % 8)) != 0,
+            _ => {
+                tracing::warn!("Failed to query the keymap");
+                false
+            }
+        }
+    }
+
+    fn key_is_toggled(&self, key: KeybdKey) -> bool {
+        let led = match key {
+            KeybdKey::CapsLockKey => 1,
+            KeybdKey::NumLockKey => 1 << 1,
+            KeybdKey::ScrollLockKey => 1 << 2,
+            _ => return false,
+        };
+        match self
+            .conn
+            .get_keyboard_control()
+            .map(|cookie| cookie.reply())
+        {
+            Ok(Ok(reply)) => reply.led_mask & led != 0,
+            _ => {
+                tracing::warn!("Failed to query the keyboard leds");
+                false
+            }
+        }
+    }
+
+    fn mouse_press(&self, button: MouseButton) {
+        self.fake_input(&[(BUTTON_PRESS_EVENT, button_code(button))]);
+    }
+
+    fn mouse_release(&self, button: MouseButton) {
+        self.fake_input(&[(BUTTON_RELEASE_EVENT, button_code(button))]);
+    }
+
+    fn mouse_tap(&self, button: MouseButton) {
+        let code = button_code(button);
+        self.fake_input(&[(BUTTON_PRESS_EVENT, code), (BUTTON_RELEASE_EVENT, code)]);
+    }
+
+    fn mouse_is_pressed(&self, button: MouseButton) -> bool {
+        let mask = match button {
+            MouseButton::LeftButton => KeyButMask::BUTTON1,
+            MouseButton::MiddleButton => KeyButMask::BUTTON2,
+            MouseButton::RightButton => KeyButMask::BUTTON3,
+            _ => return false,
+        };
+        match self
+            .conn
+            .query_pointer(self.root)
+            .map(|cookie| cookie.reply())
+        {
+            Ok(Ok(reply)) => reply.mask.contains(mask),
+            _ => {
+                tracing::warn!("Failed to query the pointer");
+                false
+            }
+        }
+    }
+
+    fn cursor_pos(&self) -> (i32, i32) {
+        match self
+            .conn
+            .query_pointer(self.root)
+            .map(|cookie| cookie.reply())
+        {
+            Ok(Ok(reply)) => (reply.root_x as i32, reply.root_y as i32),
+            _ => {
+                tracing::warn!("Failed to query the pointer");
+                (0, 0)
+            }
+        }
+    }
+
+    fn move_abs(&self, x: i32, y: i32) {
+        if let Err(err) = self.conn.xtest_fake_input(
+            MOTION_NOTIFY_EVENT,
+            0,
+            CURRENT_TIME,
+            self.root,
+            x as i16,
+            y as i16,
+            0,
+        ) {
+            tracing::warn!("Failed to move the cursor: {}", err);
+        }
+        if let Err(err) = self.conn.flush() {
+            tracing::warn!("Failed to flush the X connection: {}", err);
+        }
+    }
+
+    // X11 scrolls by clicks of buttons 4 to 7, deltas use the Windows unit of 120 per click
+    fn scroll_ver(&self, delta: i32) {
+        let button = if delta > 0 { 4 } else { 5 };
+        for _ in 0..wheel_clicks(delta) {
+            self.fake_input(&[(BUTTON_PRESS_EVENT, button), (BUTTON_RELEASE_EVENT, button)]);
+        }
+    }
+
+    fn scroll_hor(&self, delta: i32) {
+        let button = if delta > 0 { 7 } else { 6 };
+        for _ in 0..wheel_clicks(delta) {
+            self.fake_input(&[(BUTTON_PRESS_EVENT, button), (BUTTON_RELEASE_EVENT, button)]);
+        }
+    }
+
+    fn send_sequence(&self, input: &str) {
+        let shift = self.keycode(KeybdKey::LShiftKey);
+        for c in input.chars() {
+            let keysym = char_keysym(c);
+            match (self.keycode_of(keysym), shift) {
+                (Some((keycode, 0)), _) => {
+                    self.fake_input(&[(KEY_PRESS_EVENT, keycode), (KEY_RELEASE_EVENT, keycode)])
+                }
+                (Some((keycode, 1)), Some(shift)) => self.fake_input(&[
+                    (KEY_PRESS_EVENT, shift),
+                    (KEY_PRESS_EVENT, keycode),
+                    (KEY_RELEASE_EVENT, keycode),
+                    (KEY_RELEASE_EVENT, shift),
+                ]),
+                _ => {
+                    if let Err(err) = self.type_unmapped(keysym) {
+                        tracing::warn!("Failed to type '{}': {}", c, err);
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_clipboard_string(&self) -> Option<String> {
+        read_clipboard()
+            .map_err(|err| tracing::warn!("Failed to read clipboard: {}", err))
+            .ok()
+    }
+
+    fn primary_monitor_size(&self) -> anyhow::Result<(i32, i32)> {
+        Ok(self.screen_size)
+    }
+
+    fn keyboard_layout(&self) -> anyhow::Result<Option<String>> {
+        xkb_layout(&self.conn, self.root)
+    }
+
+    fn set_keyboard_layout(&self, layout: &'static KeyboardLayout) {
+        *self.layout.lock().unwrap_or_else(|err| err.into_inner()) = layout;
+    }
+
+    fn listen_once(
+        &self,
+        hotkey: Hotkey,
+        callback: Box<dyn FnOnce() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle> {
+        let id = HotkeyId::next();
+        let builder = thread::Builder::new().name(format!("{} SingleListener", &hotkey));
+        match KeyGrab::new(hotkey, self.layout()) {
+            Ok(grab) => {
+                let stop = grab.stopper();
+                builder.spawn(move || match grab.wait() {
+                    Ok(true) => callback(),
+                    Ok(false) => tracing::debug!("HotKey '{}' cancelled", hotkey),
+                    Err(err) => {
+                        tracing::error!("Failed to wait for HotKey, '{}'", err);
+                        callback();
+                    }
+                })?;
+                Ok(HotkeyHandle::new(id, hotkey, stop))
+            }
+            Err(err) => {
+                tracing::error!("Failed to bind HotKey, '{}'", err);
+                builder.spawn(callback)?;
+                Ok(HotkeyHandle::new(id, hotkey, || ()))
+            }
+        }
+    }
+
+    fn detached_hotkey(
+        &self,
+        hotkey: Hotkey,
+        callback: Box<dyn Fn() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle> {
+        let id = HotkeyId::next();
+        let grab = match KeyGrab::new(hotkey, self.layout()) {
+            Ok(grab) => grab,
+            Err(err) => {
+                tracing::error!("Failed to bind HotKey, '{}'", err);
+                return Ok(HotkeyHandle::new(id, hotkey, || ()));
+            }
+        };
+        let stop = grab.stopper();
+        thread::Builder::new()
+            .name(format!("{} DetachedHotKey", &hotkey))
+            .spawn(move || {
+                loop {
+                    match grab.wait() {
+                        Ok(true) => {
+                            tracing::info!("HotKey bound to '{}' pressed", hotkey);
+                            callback();
+                        }
+                        Ok(false) => return tracing::debug!("HotKey '{}' cancelled", hotkey),
+                        Err(_) => {
+                            return tracing::error!(
+                                "Lost connection to the X server, HotKey '{}' unbound",
+                                hotkey
+                            );
+                        }
+                    }
+                }
+            })?;
+        Ok(HotkeyHandle::new(id, hotkey, stop))
+    }
+
+    fn hook_mouse(
+        &self,
+        button: MouseButton,
+        swallow: bool,
+        callback: Box<dyn Fn() + Send + 'static>,
+    ) -> std::io::Result<HotkeyHandle> {
+        let grab = ButtonGrab::new(button, swallow).map_err(|err| {
+            std::io::Error::other(format!("Failed to hook '{:?}', '{:#}'", button, err))
+        })?;
+        let stop = grab.stopper();
+        thread::Builder::new()
+            .name(format!("{:?} MouseHook", button))
+            .spawn(move || {
+                loop {
+                    match grab.wait() {
+                        Ok(true) => {
+                            tracing::info!("Hooked mouse button '{:?}' pressed", button);
+                            callback();
+                        }
+                        Ok(false) => return tracing::debug!("Mouse hook '{:?}' cancelled", button),
+                        Err(_) => {
+                            return tracing::error!(
+                                "Lost connection to the X server, '{:?}' unhooked",
+                                button
+                            );
+                        }
+                    }
+                }
+            })?;
+        Ok(HotkeyHandle::new(HotkeyId::next(), button, stop))
+    }
+}
+
+fn wheel_clicks(delta: i32) -> u32 {
+    if delta == 0 {
+        0
+    } else {
+        ((delta.unsigned_abs() + 60) / 120).max(1)
+    }
+}
+
+struct KeyboardMapping {
+    min_keycode: Keycode,
+    keysyms_per_keycode: u8,
+    keysyms: Vec<Keysym>,
+}
+
+impl KeyboardMapping {
+    fn fetch(conn: &RustConnection) -> anyhow::Result<Self> {
+        let setup = conn.setup();
+        let (min_keycode, max_keycode) = (setup.min_keycode, setup.max_keycode);
+        let reply = conn
+            .get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)?
+            .reply()
+            .context("Failed to get the keyboard mapping")?;
+        Ok(Self {
+            min_keycode,
+            keysyms_per_keycode: reply.keysyms_per_keycode,
+            keysyms: reply.keysyms,
+        })
+    }
+
+    /// Returns the keycode producing the keysym and the column it was found in,
+    /// column 0 is unmodified and column 1 is shifted
+    fn find(&self, keysym: Keysym) -> Option<(Keycode, usize)> {
+        self.keysyms
+            .chunks(self.keysyms_per_keycode.max(1) as usize)
+            .enumerate()
+            .find_map(|(idx, syms)| {
+                syms.iter()
+                    .take(2)
+                    .position(|sym| *sym == keysym)
+                    .map(|column| (self.min_keycode + idx as u8, column))
+            })
+    }
+
+    fn unused_keycode(&self) -> Option<Keycode> {
+        self.keysyms
+            .chunks(self.keysyms_per_keycode.max(1) as usize)
+            .rposition(|syms| syms.iter().all(|sym| *sym == 0))
+            .map(|idx| self.min_keycode + idx as u8)
+    }
+}
+
+/// Key combination grabbed on the root window of its own connection, ungrabbed on drop
+struct KeyGrab {
+    conn: Arc<RustConnection>,
+    root: Window,
+    keycode: Keycode,
+    modifiers: ModMask,
+    // receives the message that stops `wait`
+    waker: Window,
+}
+
+impl KeyGrab {
+    fn new(hotkey: Hotkey, layout: &KeyboardLayout) -> anyhow::Result<Self> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen_num].root;
+        let keysym = keysym(hotkey.key, layout)
+            .with_context(|| format!("'{:?}' is not part of the {} layout", hotkey.key, layout))?;
+        let (keycode, _) = KeyboardMapping::fetch(&conn)?
+            .find(keysym)
+            .with_context(|| format!("No keycode found for '{:?}'", hotkey.key))?;
+        let modifiers = mod_mask(hotkey.modifiers);
+        for ignored in ignored_modifiers() {
+            conn.grab_key(
+                false,
+                root,
+                modifiers | ignored,
+                keycode,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+            )?
+            .check()
+            .with_context(|| format!("'{}' is already grabbed by another client", hotkey))?;
+        }
+        let waker = create_waker(&conn, root)?;
+        Ok(Self {
+            conn: Arc::new(conn),
+            root,
+            keycode,
+            modifiers,
+            waker,
+        })
+    }
+
+    /// Blocks until the key combination is pressed, returns false if stopped beforehand
+    fn wait(&self) -> anyhow::Result<bool> {
+        loop {
+            match self.conn.wait_for_event()? {
+                Event::KeyPress(event) if event.detail == self.keycode => return Ok(true),
+                Event::ClientMessage(event) if event.window == self.waker => return Ok(false),
+                _ => (),
+            }
+        }
+    }
+
+    /// Returns a function stopping `wait` from any thread, the grab is released
+    /// once the waiting thread drops it
+    fn stopper(&self) -> impl FnOnce() + Send + 'static {
+        stopper(self.conn.clone(), self.waker)
+    }
+}
+
+impl Drop for KeyGrab {
+    fn drop(&mut self) {
+        for ignored in ignored_modifiers() {
+            let _ = self
+                .conn
+                .ungrab_key(self.keycode, self.root, self.modifiers | ignored);
+        }
+        let _ = self.conn.destroy_window(self.waker);
+        let _ = self.conn.flush();
+    }
+}
+
+/// Mouse button grabbed on the root window of its own connection, ungrabbed on drop.
+/// Unless swallowed, every press is replayed to the window under the cursor.
+struct ButtonGrab {
+    conn: Arc<RustConnection>,
+    root: Window,
+    button: ButtonIndex,
+    swallow: bool,
+    // receives the message that stops `wait`
+    waker: Window,
+}
+
+impl ButtonGrab {
+    fn new(button: MouseButton, swallow: bool) -> anyhow::Result<Self> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen_num].root;
+        let index = ButtonIndex::from(button_code(button));
+        // a synchronous grab freezes the pointer until the press is replayed or swallowed
+        let pointer_mode = if swallow {
+            GrabMode::ASYNC
+        } else {
+            GrabMode::SYNC
+        };
+        conn.grab_button(
+            false,
+            root,
+            EventMask::BUTTON_PRESS | EventMask::BUTTON_RELEASE,
+            pointer_mode,
+            GrabMode::ASYNC,
+            NONE,
+            NONE,
+            index,
+            ModMask::ANY,
+        )?
+        .check()
+        .with_context(|| format!("'{:?}' is already grabbed by another client", button))?;
+        let waker = create_waker(&conn, root)?;
+        Ok(Self {
+            conn: Arc::new(conn),
+            root,
+            button: index,
+            swallow,
+            waker,
+        })
+    }
+
+    /// Blocks until the button is pressed, returns false if stopped beforehand
+    fn wait(&self) -> anyhow::Result<bool> {
+        loop {
+            match self.conn.wait_for_event()? {
+                Event::ButtonPress(event) if event.detail == u8::from(self.button) => {
+                    if !self.swallow {
+                        self.conn.allow_events(Allow::REPLAY_POINTER, event.time)?;
+                        self.conn.flush()?;
+                    }
+                    return Ok(true);
+                }
+                Event::ClientMessage(event) if event.window == self.waker => return Ok(false),
+                _ => (),
+            }
+        }
+    }
+
+    fn stopper(&self) -> impl FnOnce() + Send + 'static {
+        stopper(self.conn.clone(), self.waker)
+    }
+}
+
+impl Drop for ButtonGrab {
+    fn drop(&mut self) {
+        let _ = self
+            .conn
+            .ungrab_button(self.button, self.root, ModMask::ANY);
+        let _ = self.conn.destroy_window(self.waker);
+        let _ = self.conn.flush();
+    }
+}
+
+/// Creates an invisible window whose only purpose is to receive the message stopping
+/// a grab from waiting
+fn create_waker(conn: &RustConnection, root: Window) -> anyhow::Result<Window> {
+    let waker = conn.generate_id()?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        waker,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        x11rb::COPY_FROM_PARENT,
+        &CreateWindowAux::new(),
+    )?
+    .check()?;
+    Ok(waker)
+}
+
+fn stopper(conn: Arc<RustConnection>, waker: Window) -> impl FnOnce() + Send + 'static {
+    move || {
+        // without an event mask, the event is sent to the client that created the window
+        let event = ClientMessageEvent::new(32, waker, AtomEnum::NONE, [0_u32; 5]);
+        if let Err(err) = conn
+            .send_event(false, waker, EventMask::NO_EVENT, event)
+            .map(|_| conn.flush())
+        {
+            tracing::warn!("Failed to stop waiting for a HotKey: {}", err);
+        }
+    }
+}
+
+// longest wait for the owner of the clipboard, which may never answer
+const CLIPBOARD_TIMEOUT: Duration = Duration::from_secs(1);
+
+fn read_clipboard() -> anyhow::Result<String> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let window = conn.generate_id()?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        x11rb::COPY_FROM_PARENT,
+        // an owner sending the clipboard in chunks announces each of them with a PropertyNotify
+        &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+    )?;
+    let clipboard = conn.intern_atom(false, b"CLIPBOARD")?.reply()?.atom;
+    let utf8_string = conn.intern_atom(false, b"UTF8_STRING")?.reply()?.atom;
+    let property = conn.intern_atom(false, b"BA_CLIPBOARD")?.reply()?.atom;
+    let incr = conn.intern_atom(false, b"INCR")?.reply()?.atom;
+    conn.convert_selection(window, clipboard, utf8_string, property, CURRENT_TIME)?;
+    conn.flush()?;
+
+    // the server answers with a property of None if nobody owns the clipboard
+    let deadline = Instant::now() + CLIPBOARD_TIMEOUT;
+    loop {
+        match conn.poll_for_event()? {
+            Some(Event::SelectionNotify(event)) if event.requestor == window => {
+                if event.property == NONE {
+                    Err(anyhow::anyhow!("The clipboard is empty"))?
+                }
+                // deleting the property also asks an incremental owner for the first chunk
+                let reply = conn
+                    .get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX / 4)?
+                    .reply()?;
+                let value = match reply.type_ == incr {
+                    true => read_incremental(&conn, window, property)?,
+                    false => reply.value,
+                };
+                return Ok(String::from_utf8_lossy(&value).to_string());
+            }
+            Some(_) => (),
+            None if Instant::now() >= deadline => Err(anyhow::anyhow!(
+                "The owner of the clipboard did not answer within {} ms",
+                CLIPBOARD_TIMEOUT.as_millis()
+            ))?,
+            None => thread::sleep(Duration::from_millis(5)),
+        }
+    }
+}
+
+/// Receives a clipboard too large to be sent at once (INCR protocol), the owner writes it to
+/// `property` one chunk at a time, waiting for each one to be deleted, and ends with an empty one
+fn read_incremental(
+    conn: &RustConnection,
+    window: Window,
+    property: Atom,
+) -> anyhow::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut deadline = Instant::now() + CLIPBOARD_TIMEOUT;
+    loop {
+        match conn.poll_for_event()? {
+            Some(Event::PropertyNotify(event))
+                if event.window == window
+                    && event.atom == property
+                    && event.state == Property::NEW_VALUE =>
+            {
+                let chunk = conn
+                    .get_property(true, window, property, AtomEnum::ANY, 0, u32::MAX / 4)?
+                    .reply()?;
+                if chunk.value.is_empty() {
+                    tracing::debug!("Clipboard of {} bytes received in chunks", data.len());
+                    return Ok(data);
+                }
+                data.extend_from_slice(&chunk.value);
+                deadline = Instant::now() + CLIPBOARD_TIMEOUT;
+            }
+            Some(_) => (),
+            None if Instant::now() >= deadline => Err(anyhow::anyhow!(
+                "The owner of the clipboard stopped sending it after {} bytes",
+                data.len()
+            ))?,
+            None => thread::sleep(Duration::from_millis(5)),
+        }
+    }
+}
+
+/// Layout and variant of the first XKB group, e.g. "ch(fr)", as configured on the root window
+fn xkb_layout(conn: &RustConnection, root: Window) -> anyhow::Result<Option<String>> {
+    let atom = conn.intern_atom(true, b"_XKB_RULES_NAMES")?.reply()?.atom;
+    if atom == NONE {
+        return Ok(None);
+    }
+    let reply = conn
+        .get_property(false, root, atom, AtomEnum::STRING, 0, 1024)?
+        .reply()?;
+    Ok(parse_xkb_layout(&reply.value))
+}
+
+// rules, model, layouts, variants and options separated by NUL,
+// the layouts and variants of every group separated by commas
+fn parse_xkb_layout(names: &[u8]) -> Option<String> {
+    let names = String::from_utf8_lossy(names);
+    let mut fields = names.split('\0').skip(2);
+    let layout = fields
+        .next()
+        .and_then(|f| f.split(',').next())
+        .unwrap_or("");
+    let variant = fields
+        .next()
+        .and_then(|f| f.split(',').next())
+        .unwrap_or("");
+    match (layout, variant) {
+        ("", _) => None,
+        (layout, "") => Some(layout.to_string()),
+        (layout, variant) => Some(format!("{layout}({variant})")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapping_lookup() {
+        // keycodes 8 to 11, two keysyms per keycode
+        let mapping = KeyboardMapping {
+            min_keycode: 8,
+            keysyms_per_keycode: 2,
+            keysyms: vec![0x61, 0x41, 0, 0, 0xFF1B, 0, 0, 0],
+        };
+        assert_eq!(mapping.find(0x61), Some((8, 0)));
+        assert_eq!(mapping.find(0x41), Some((8, 1)));
+        assert_eq!(
+            mapping.find(keysym(KeybdKey::EscapeKey, SWISS_FRENCH).unwrap()),
+            Some((10, 0))
+        );
+        assert_eq!(mapping.find(0x62), None);
+        assert_eq!(mapping.unused_keycode(), Some(11));
+    }
+
+    #[test]
+    fn xkb_layouts() {
+        assert_eq!(
+            parse_xkb_layout(b"evdev\0pc105\0ch\0fr\0\0"),
+            Some("ch(fr)".to_string())
+        );
+        assert_eq!(
+            parse_xkb_layout(b"evdev\0pc105\0us,de\0,\0grp:alt_shift_toggle\0"),
+            Some("us".to_string())
+        );
+        assert_eq!(parse_xkb_layout(b"evdev\0pc105\0\0\0\0"), None);
+    }
+
+    #[test]
+    fn layout_keysyms() {
+        let us = KeyboardLayout::by_name("us").unwrap();
+        assert_eq!(keysym(KeybdKey::OEM1, SWISS_FRENCH), Some(0x00E8));
+        assert_eq!(keysym(KeybdKey::OEM1, us), Some(0x003B));
+        assert_eq!(keysym(KeybdKey::OEM8, us), None);
+        assert_eq!(keysym(KeybdKey::AKey, us), Some(0x0061));
+    }
+
+    #[test]
+    fn wheel_deltas() {
+        assert_eq!(wheel_clicks(0), 0);
+        assert_eq!(wheel_clicks(30), 1);
+        assert_eq!(wheel_clicks(-120), 1);
+        assert_eq!(wheel_clicks(400), 3);
+    }
+
+    // runs against whatever X server DISPLAY points to, e.g. `xvfb-run cargo test -- --ignored`
+    #[test]
+    #[ignore = "needs an X server"]
+    fn xvfb_roundtrip() {
+        let backend = X11Backend::new().expect("DISPLAY must point to an X server");
+        backend.move_abs(10, 20);
+        assert_eq!(backend.cursor_pos(), (10, 20));
+        backend.key_press(KeybdKey::LShiftKey);
+        assert!(backend.key_is_pressed(KeybdKey::LShiftKey));
+        backend.key_release(KeybdKey::LShiftKey);
+        assert!(!backend.key_is_pressed(KeybdKey::LShiftKey));
+    }
+}